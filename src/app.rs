@@ -1,28 +1,149 @@
-use std::mem;
+use std::{
+    collections::VecDeque,
+    io, mem,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Duration,
+};
 
-use color_eyre::Result;
+use color_eyre::{Result, eyre};
 use ratatui::{
     DefaultTerminal,
     crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
     prelude::*,
 };
+use smol::{
+    LocalExecutor, Task, Timer,
+    channel::{self, Receiver, Sender},
+    stream::StreamExt,
+};
+
+use crate::{
+    error_dialog::{ErrorAction, ErrorDialog},
+    scaffold::{DryRunEntry, GenerationError, GenerationProgress},
+    tabs::{Tab, search::ScaffoldTab},
+    template_info::{ArcStr, TemplateStructure},
+};
 
-use crate::tabs::{Tab, search::ScaffoldTab};
+/// How often the screen is redrawn while nothing else happens, so spinners and progress bars keep moving
+const TICK_RATE: Duration = Duration::from_millis(100);
+/// How long the input thread waits for a terminal event before checking whether the app quit
+const INPUT_POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Everything the event loop reacts to
+pub enum AppEvent {
+    Terminal(io::Result<Event>),
+    Tick,
+    /// Sent by a task spawned by the tab with the given id
+    Task(TabId, TaskEvent),
+}
+
+/// Identifies an open tab, so the events of the tasks it spawned reach it even after it was cached
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TabId(u64);
+impl TabId {
+    fn new() -> Self {
+        static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(0);
+        TabId(NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+struct OpenTab {
+    id: TabId,
+    tab: Box<dyn Tab>,
+    /// Events sent to the tab while it was cached, handled once it's current again
+    pending_task_events: VecDeque<TaskEvent>,
+}
+impl OpenTab {
+    fn new(id: TabId, tab: Box<dyn Tab>) -> Self {
+        OpenTab {
+            id,
+            tab,
+            pending_task_events: VecDeque::new(),
+        }
+    }
+}
+
+/// Sent by tasks running in the background to the tab that spawned them
+pub enum TaskEvent {
+    CatalogLoaded(eyre::Result<()>),
+    TemplateStructureLoaded {
+        template_id: ArcStr,
+        result: eyre::Result<TemplateStructure>,
+    },
+    ReadmeLoaded {
+        template_id: ArcStr,
+        result: eyre::Result<String>,
+    },
+    DryRunLoaded(eyre::Result<Vec<DryRunEntry>>),
+    GenerationProgressed(GenerationProgress),
+    /// Some entries could not be generated, and the generation waits for them to be retried
+    GenerationEntriesFailed(GenerationError),
+    GenerationFinished(eyre::Result<()>),
+}
+
+/// Lets background tasks send [`TaskEvent`]s to the event loop, addressed to the tab that spawned them
+#[derive(Clone)]
+pub struct TaskEventSender {
+    event_sender: Sender<AppEvent>,
+    tab_id: TabId,
+}
+impl TaskEventSender {
+    pub fn send(&self, task_event: TaskEvent) {
+        // The channel is unbounded, so this only fails once the app has quit
+        let _ = self
+            .event_sender
+            .try_send(AppEvent::Task(self.tab_id, task_event));
+    }
+}
 
-#[derive(Default)]
 pub struct Commands {
-    pub next_tab: Option<Box<dyn Tab>>,
+    next_tab: Option<OpenTab>,
     pub should_switch_tab_to_cached: bool,
     pub should_cache_current_tab: bool,
     pub should_quit: bool,
     pub completion_cb: Option<Box<dyn FnOnce()>>,
+    pub error_dialog: Option<ErrorDialog>,
+    executor: Rc<LocalExecutor<'static>>,
+    /// Tags the events of spawned tasks with the tab the commands are for
+    task_event_sender: TaskEventSender,
 }
 impl Commands {
+    fn new(
+        executor: Rc<LocalExecutor<'static>>,
+        event_sender: Sender<AppEvent>,
+        tab_id: TabId,
+    ) -> Self {
+        Self {
+            next_tab: None,
+            should_switch_tab_to_cached: false,
+            should_cache_current_tab: false,
+            should_quit: false,
+            completion_cb: None,
+            error_dialog: None,
+            executor,
+            task_event_sender: TaskEventSender {
+                event_sender,
+                tab_id,
+            },
+        }
+    }
     pub fn cache_current_tab(&mut self) {
         self.should_cache_current_tab = true;
     }
-    pub fn switch_tab_to(&mut self, next_tab: impl Tab + 'static) {
-        self.next_tab = Some(Box::new(next_tab));
+    /// Replaces the current tab with the one `make_next_tab` builds, whose tasks report to the new tab
+    pub fn switch_tab_to<T: Tab + 'static>(&mut self, make_next_tab: impl FnOnce(&Commands) -> T) {
+        let next_tab_commands = Commands::new(
+            self.executor.clone(),
+            self.task_event_sender.event_sender.clone(),
+            TabId::new(),
+        );
+        let next_tab = make_next_tab(&next_tab_commands);
+        self.next_tab = Some(OpenTab::new(
+            next_tab_commands.task_event_sender.tab_id,
+            Box::new(next_tab),
+        ));
     }
     pub fn quit(&mut self, completion_cb: Option<Box<dyn FnOnce()>>) {
         self.should_quit = true;
@@ -31,56 +152,171 @@ impl Commands {
     pub fn switch_tab_to_cached(&mut self) {
         self.should_switch_tab_to_cached = true;
     }
+    /// Shows `error_dialog` over the current tab, which gets the picked action through [`Tab::handle_error_action`]
+    pub fn show_error(&mut self, error_dialog: ErrorDialog) {
+        self.error_dialog = Some(error_dialog);
+    }
+    /// Runs `spawn_future` with a [`TaskEventSender`] alongside the event loop, on the main thread.
+    /// The task is cancelled once the returned [`Task`] is dropped.
+    pub fn spawn<F: Future<Output = ()> + 'static>(
+        &self,
+        spawn_future: impl FnOnce(TaskEventSender) -> F,
+    ) -> Task<()> {
+        self.executor
+            .spawn(spawn_future(self.task_event_sender.clone()))
+    }
 }
 
 pub struct App {
-    current_tab: Box<dyn Tab>,
-    cached_tab: Box<dyn Tab>,
+    current_tab: OpenTab,
+    cached_tab: OpenTab,
+    error_dialog: Option<ErrorDialog>,
     pub on_complete: Option<Box<dyn FnOnce()>>,
 }
 impl App {
     pub fn new() -> Self {
         Self {
-            current_tab: Box::new(ScaffoldTab::new()),
-            cached_tab: Box::new(ScaffoldTab::new()),
+            current_tab: OpenTab::new(TabId::new(), Box::new(ScaffoldTab::new())),
+            cached_tab: OpenTab::new(TabId::new(), Box::new(ScaffoldTab::new())),
+            error_dialog: None,
             on_complete: None,
         }
     }
 
     pub fn run(&mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        let executor = Rc::new(LocalExecutor::new());
+        let (event_sender, event_receiver) = channel::unbounded();
+        let input_thread = spawn_input_thread(event_sender.clone());
+        let ticker = executor.spawn(send_ticks(event_sender.clone()));
+        let result = smol::block_on(executor.run(self.run_event_loop(
+            &mut terminal,
+            &executor,
+            &event_sender,
+            &event_receiver,
+        )));
+        drop(ticker);
+        // Stops the input thread, so it doesn't read input meant for whatever runs after the app
+        event_receiver.close();
+        let _ = input_thread.join();
+        result
+    }
+    async fn run_event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        executor: &Rc<LocalExecutor<'static>>,
+        event_sender: &Sender<AppEvent>,
+        event_receiver: &Receiver<AppEvent>,
+    ) -> Result<()> {
+        let mut commands =
+            Commands::new(executor.clone(), event_sender.clone(), self.current_tab.id);
+        self.current_tab.tab.handle_start(&mut commands);
+        self.apply_commands(commands);
         loop {
             terminal.draw(|frame| self.render(frame.area(), frame.buffer_mut()))?;
-            let ev = event::read()?;
-            match ev {
-                Event::Key(key) => {
-                    if key.kind == KeyEventKind::Press
+            let Ok(app_event) = event_receiver.recv().await else {
+                return Ok(());
+            };
+            let mut commands =
+                Commands::new(executor.clone(), event_sender.clone(), self.current_tab.id);
+            match app_event {
+                AppEvent::Terminal(ev) => {
+                    let ev = ev?;
+                    if let Event::Key(key) = ev
+                        && key.kind == KeyEventKind::Press
                         && key.code == KeyCode::Char('c')
                         && key.modifiers.contains(KeyModifiers::CONTROL)
                     {
                         return Ok(());
                     }
+                    if let Some(error_dialog) = &mut self.error_dialog {
+                        match error_dialog.handle_event(&ev) {
+                            Some(ErrorAction::Quit) => return Ok(()),
+                            Some(error_action) => {
+                                self.error_dialog = None;
+                                self.current_tab
+                                    .tab
+                                    .handle_error_action(error_action, &mut commands);
+                            }
+                            None => {}
+                        }
+                    } else {
+                        self.current_tab.tab.handle_event(ev, &mut commands);
+                    }
+                }
+                AppEvent::Tick => {}
+                AppEvent::Task(tab_id, task_event) => {
+                    if tab_id == self.current_tab.id {
+                        self.current_tab
+                            .tab
+                            .handle_task_event(task_event, &mut commands);
+                    } else if tab_id == self.cached_tab.id {
+                        self.cached_tab.pending_task_events.push_back(task_event);
+                    }
+                    // Events of tasks whose tab was closed are dropped
                 }
-                _ => {}
-            }
-            let mut commands = Commands::default();
-            self.current_tab.handle_event(ev, &mut commands);
-
-            if commands.should_cache_current_tab {
-                mem::swap(&mut self.cached_tab, &mut self.current_tab);
-            }
-            if let Some(next_tab) = commands.next_tab {
-                self.current_tab = next_tab;
-            }
-            if commands.should_switch_tab_to_cached {
-                mem::swap(&mut self.cached_tab, &mut self.current_tab);
             }
-            self.on_complete = commands.completion_cb;
-            if commands.should_quit {
+            if self.apply_commands(commands) {
                 return Ok(());
             }
+            while let Some(task_event) = self.current_tab.pending_task_events.pop_front() {
+                let mut commands =
+                    Commands::new(executor.clone(), event_sender.clone(), self.current_tab.id);
+                self.current_tab
+                    .tab
+                    .handle_task_event(task_event, &mut commands);
+                if self.apply_commands(commands) {
+                    return Ok(());
+                }
+            }
         }
     }
+    /// Carries out what a tab asked for, returning whether the app should quit.
+    fn apply_commands(&mut self, commands: Commands) -> bool {
+        if commands.should_cache_current_tab {
+            mem::swap(&mut self.cached_tab, &mut self.current_tab);
+        }
+        if let Some(next_tab) = commands.next_tab {
+            self.current_tab = next_tab;
+        }
+        if commands.should_switch_tab_to_cached {
+            mem::swap(&mut self.cached_tab, &mut self.current_tab);
+        }
+        if let Some(error_dialog) = commands.error_dialog {
+            self.error_dialog = Some(error_dialog);
+        }
+        self.on_complete = commands.completion_cb;
+        commands.should_quit
+    }
     pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
-        self.current_tab.render(area, buf);
+        self.current_tab.tab.render(area, buf);
+        if let Some(error_dialog) = &self.error_dialog {
+            error_dialog.render(area, buf);
+        }
+    }
+}
+
+/// Reads terminal events on their own thread, since crossterm can only wait for them by blocking
+fn spawn_input_thread(event_sender: Sender<AppEvent>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while !event_sender.is_closed() {
+            let ev = match event::poll(INPUT_POLL_TIMEOUT) {
+                Ok(true) => event::read(),
+                Ok(false) => continue,
+                Err(err) => Err(err),
+            };
+            let is_err = ev.is_err();
+            if event_sender.send_blocking(AppEvent::Terminal(ev)).is_err() || is_err {
+                return;
+            }
+        }
+    })
+}
+
+async fn send_ticks(event_sender: Sender<AppEvent>) {
+    let mut ticks = Timer::interval(TICK_RATE);
+    while ticks.next().await.is_some() {
+        if event_sender.send(AppEvent::Tick).await.is_err() {
+            return;
+        }
     }
 }
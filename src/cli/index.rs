@@ -0,0 +1,148 @@
+use std::{fs, io, path::PathBuf};
+
+use clap::Args;
+use color_eyre::eyre::{self, WrapErr};
+
+use crate::{
+    template_index::{
+        TEMPLATE_CONTENTS_DIR_NAME, apply_conditions, index_template, serialize_template_structure,
+    },
+    template_info::{TemplateInfo, TemplateStructure},
+};
+
+#[derive(Args, Debug)]
+pub struct IndexArgs {
+    /// The templates directory, containing `templates.json` and a folder for each template
+    #[arg(default_value = "templates")]
+    pub templates_dir: PathBuf,
+    /// Fails instead of writing if any committed structure index is missing or out of date
+    #[arg(long)]
+    pub check: bool,
+}
+
+pub fn run(args: IndexArgs) -> eyre::Result<()> {
+    let contents_dir = args.templates_dir.join(TEMPLATE_CONTENTS_DIR_NAME);
+    let mut template_names = vec![];
+    for dir_entry in fs::read_dir(&args.templates_dir)
+        .wrap_err_with(|| format!("could not read {}", args.templates_dir.display()))?
+    {
+        let dir_entry = dir_entry?;
+        let template_name = dir_entry.file_name().to_string_lossy().into_owned();
+        if dir_entry.file_type()?.is_dir() && template_name != TEMPLATE_CONTENTS_DIR_NAME {
+            template_names.push(template_name);
+        }
+    }
+    template_names.sort_unstable();
+
+    let templates_json_path = args.templates_dir.join("templates.json");
+    let template_infos = match fs::read(&templates_json_path) {
+        Ok(unparsed) => serde_json::from_slice::<Vec<TemplateInfo>>(&unparsed)
+            .wrap_err_with(|| format!("invalid {}", templates_json_path.display()))?,
+        // Without a templates.json, there are no conditions to copy into the indexes
+        Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("could not read {}", templates_json_path.display()));
+        }
+    };
+
+    let mut stale_indexes = vec![];
+    for template_name in &template_names {
+        let mut structure = index_template(&args.templates_dir.join(template_name))
+            .wrap_err_with(|| format!("could not index template {template_name}"))?;
+        if let Some(template_info) = template_infos
+            .iter()
+            .find(|template_info| *template_info.path == **template_name)
+        {
+            apply_conditions(&mut structure, template_info)
+                .wrap_err_with(|| format!("invalid conditions for template {template_name}"))?;
+        }
+        let index_path = contents_dir.join(format!("{template_name}.json"));
+        if args.check {
+            let committed_structure = fs::read(&index_path)
+                .ok()
+                .and_then(|unparsed| serde_json::from_slice::<TemplateStructure>(&unparsed).ok());
+            if committed_structure.as_ref() != Some(&structure) {
+                stale_indexes.push(index_path.display().to_string());
+            }
+        } else {
+            fs::create_dir_all(&contents_dir)?;
+            fs::write(&index_path, serialize_template_structure(&structure)?)
+                .wrap_err_with(|| format!("could not write {}", index_path.display()))?;
+            println!("Indexed {template_name}");
+        }
+    }
+
+    // Indexes left behind by templates whose folder was removed or renamed
+    let mut orphan_indexes = vec![];
+    if let Ok(index_entries) = fs::read_dir(&contents_dir) {
+        for index_entry in index_entries {
+            let index_path = index_entry?.path();
+            let has_template = index_path.file_stem().is_some_and(|file_stem| {
+                template_names
+                    .iter()
+                    .any(|template_name| **template_name == *file_stem)
+            });
+            if !has_template {
+                orphan_indexes.push(index_path);
+            }
+        }
+    }
+    orphan_indexes.sort_unstable();
+
+    if args.check {
+        stale_indexes.extend(
+            orphan_indexes
+                .iter()
+                .map(|index_path| index_path.display().to_string()),
+        );
+        if !stale_indexes.is_empty() {
+            eyre::bail!(
+                "these structure indexes are out of date, run `scaffy index` to regenerate them:\n{}",
+                stale_indexes.join("\n")
+            );
+        }
+        println!(
+            "All {} structure indexes are up to date",
+            template_names.len()
+        );
+    } else {
+        for index_path in orphan_indexes {
+            fs::remove_file(&index_path)
+                .wrap_err_with(|| format!("could not remove {}", index_path.display()))?;
+            println!("Removed {}", index_path.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{IndexArgs, run};
+
+    #[test]
+    fn removes_indexes_of_removed_templates() {
+        let templates_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(templates_dir.path().join("kept")).unwrap();
+        fs::write(templates_dir.path().join("kept/README.md"), "# Kept").unwrap();
+        let contents_dir = templates_dir.path().join("__scaffy_template_contents");
+        fs::create_dir(&contents_dir).unwrap();
+        fs::write(contents_dir.join("removed.json"), "{}").unwrap();
+        let index_args = |check| IndexArgs {
+            templates_dir: templates_dir.path().to_path_buf(),
+            check,
+        };
+
+        assert!(run(index_args(true)).is_err());
+        run(index_args(false)).unwrap();
+        assert!(contents_dir.join("kept.json").exists());
+        assert!(!contents_dir.join("removed.json").exists());
+        run(index_args(true)).unwrap();
+
+        // Only a missing templates.json is fine to index without
+        fs::create_dir(templates_dir.path().join("templates.json")).unwrap();
+        assert!(run(index_args(false)).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use clap::Args;
+use color_eyre::eyre;
+use serde::Serialize;
+
+use crate::{
+    registry::stale_registry_names,
+    tabs::{
+        query::SearchQuery,
+        search::ScaffoldListEntry,
+        tag::{Tag, parse_template_info_tags},
+    },
+    template_info::{ArcStr, TEMPLATE_INFOS, fetch_template_info},
+};
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Prints the templates as a JSON array instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// Space-separated, case-insensitive words that must each fuzzily match a template's name, description, tags, author or registry,
+    /// with the same syntax as the searchbar, e.g. `lang:rust -tag:wasm`. Templates are listed from the best match to the worst.
+    #[arg(required = true)]
+    pub query: Vec<String>,
+    /// Prints the matching templates as a JSON array instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Serialize)]
+struct TemplateListing {
+    id: ArcStr,
+    registry: ArcStr,
+    name: ArcStr,
+    path: ArcStr,
+    author: ArcStr,
+    description: ArcStr,
+    tags: Vec<Tag>,
+}
+
+pub fn run_list(args: ListArgs) -> eyre::Result<()> {
+    print_templates("", args.json)
+}
+
+pub fn run_search(args: SearchArgs) -> eyre::Result<()> {
+    print_templates(&args.query.join(" "), args.json)
+}
+
+fn print_templates(query: &str, as_json: bool) -> eyre::Result<()> {
+    fetch_template_info()?;
+    for registry_name in stale_registry_names() {
+        eprintln!("warning: registry {registry_name} is unreachable, using its cached catalog");
+    }
+    let search_query = SearchQuery::parse(query);
+    let mut ranked_listings = TEMPLATE_INFOS.with_borrow(|template_infos| {
+        template_infos
+            .values()
+            .filter_map(|template_info| {
+                let query_match = ScaffoldListEntry::from_template_info(template_info)
+                    .match_query(&search_query)?;
+                Some((query_match.rank(), template_info))
+            })
+            .map(|(rank, template_info)| {
+                let listing = TemplateListing {
+                    id: template_info.id.clone(),
+                    registry: template_info.registry.clone(),
+                    name: template_info.name.clone(),
+                    path: template_info.path.clone(),
+                    author: template_info.author.clone(),
+                    description: template_info.description.clone(),
+                    tags: parse_template_info_tags(&template_info.tags),
+                };
+                (rank, listing)
+            })
+            .collect::<Vec<_>>()
+    });
+    // Best matches first, like in the TUI, and by id among equally good ones
+    ranked_listings.sort_unstable_by(|(rank_1, listing_1), (rank_2, listing_2)| {
+        rank_2
+            .cmp(rank_1)
+            .then_with(|| listing_1.id.cmp(&listing_2.id))
+    });
+    let template_listings = ranked_listings
+        .into_iter()
+        .map(|(_, listing)| listing)
+        .collect::<Vec<_>>();
+
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&template_listings)?);
+    } else {
+        print_table(&template_listings);
+    }
+    Ok(())
+}
+
+fn print_table(template_listings: &[TemplateListing]) {
+    let rows = template_listings
+        .iter()
+        .map(|listing| {
+            let tags = listing
+                .tags
+                .iter()
+                .map(|tag| match &tag.version {
+                    Some(version) => format!("{}@{}", tag.text, version),
+                    None => tag.text.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            [
+                listing.id.to_string(),
+                listing.name.to_string(),
+                listing.author.to_string(),
+                listing.registry.to_string(),
+                tags,
+                listing.description.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["ID", "NAME", "AUTHOR", "REGISTRY", "TAGS", "DESCRIPTION"].map(String::from);
+    let mut column_widths = [0; 6];
+    for row in rows.iter().chain([&header]) {
+        for (column_width, cell) in column_widths.iter_mut().zip(row) {
+            *column_width = (*column_width).max(cell.chars().count());
+        }
+    }
+    for row in [&header].into_iter().chain(&rows) {
+        let line = row
+            .iter()
+            .zip(column_widths)
+            .map(|(cell, column_width)| format!("{cell:column_width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+}
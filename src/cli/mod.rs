@@ -0,0 +1,47 @@
+pub mod index;
+pub mod list;
+pub mod new;
+pub mod update;
+
+use clap::{Parser, Subcommand};
+use color_eyre::eyre;
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Where to load templates from; either a URL or a local directory laid out like `/templates`.
+    /// Takes precedence over the `SCAFFY_SOURCE` environment variable
+    #[arg(long, global = true)]
+    pub source: Option<String>,
+    /// Additional template registry to merge into the list, as NAME=SOURCE; can be repeated
+    #[arg(long = "registry", value_name = "NAME=SOURCE", global = true)]
+    pub registries: Vec<String>,
+    /// Runs a command without starting the TUI
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Scaffolds a project from a template non-interactively
+    New(new::NewArgs),
+    /// Lists every available template
+    List(list::ListArgs),
+    /// Lists the templates matching a query, like the TUI's searchbar
+    Search(list::SearchArgs),
+    /// Merges changes made to a project's template since it was generated into the project
+    Update(update::UpdateArgs),
+    /// Regenerates the structure index of every template in a templates directory
+    Index(index::IndexArgs),
+}
+impl CliCommand {
+    pub fn run(self) -> eyre::Result<()> {
+        match self {
+            CliCommand::New(args) => new::run(args),
+            CliCommand::List(args) => list::run_list(args),
+            CliCommand::Search(args) => list::run_search(args),
+            CliCommand::Update(args) => update::run(args),
+            CliCommand::Index(args) => index::run(args),
+        }
+    }
+}
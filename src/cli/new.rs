@@ -0,0 +1,177 @@
+use std::{
+    collections::HashSet,
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use color_eyre::eyre;
+
+use crate::{
+    scaffold::{
+        ExistingFiles, ExistingFilesMode, PlannedAction, PlannedEntryKind, dry_run_project,
+        find_existing_files, init_project, is_nonempty_dir, plan_project,
+    },
+    string_ops::{ScaffyReplacer, WordCasing, string_to_casing},
+    template_info::{fetch_template_info, get_template_info},
+    template_vars::{VariableValues, resolve_variables},
+};
+
+#[derive(Args, Debug)]
+pub struct NewArgs {
+    /// Id of the template to use, as shown in the TUI (`path` or `registry/path`)
+    pub template: String,
+    /// Name of the project, substituted into the template's placeholders
+    #[arg(long)]
+    pub name: String,
+    /// Directory to create the project in; defaults to the project name in kebab case inside the current directory
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+    /// Value of one of the template's variables, as NAME=VALUE; can be repeated, and unset variables take their defaults
+    #[arg(long = "var", value_name = "NAME=VALUE")]
+    pub vars: Vec<String>,
+    /// What to do if the project directory already exists and is not empty
+    #[arg(long, value_enum, default_value_t = ExistingFilesMode::Abort)]
+    pub on_existing: ExistingFilesMode,
+    /// Print every file that would be written, with its size and whether placeholders are replaced in it, without writing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run(args: NewArgs) -> eyre::Result<()> {
+    if args.name.trim().is_empty() {
+        eyre::bail!("the project name must not be empty");
+    }
+    let project_root_dir = match args.dir {
+        Some(dir) => dir,
+        None => {
+            env::current_dir()?.join(string_to_casing(&args.name, "-", WordCasing::Lower, None))
+        }
+    };
+    fetch_template_info()?;
+    let template_info = get_template_info(&args.template)?;
+    let mut answers = vec![];
+    for var in &args.vars {
+        let Some(answer) = var.split_once('=') else {
+            eyre::bail!("invalid --var {var}; use NAME=VALUE");
+        };
+        answers.push(answer);
+    }
+    let variable_values = resolve_variables(&template_info.variables, answers)?;
+    let existing_files = match args.on_existing {
+        ExistingFilesMode::Abort => ExistingFiles::Abort,
+        ExistingFilesMode::Skip => ExistingFiles::Skip,
+        ExistingFilesMode::Overwrite => ExistingFiles::Overwrite,
+        ExistingFilesMode::Ask => ask_existing_files(
+            &template_info.id,
+            &args.name,
+            &variable_values,
+            &project_root_dir,
+        )?,
+    };
+    if args.dry_run {
+        return print_dry_run(
+            &template_info.id,
+            &args.name,
+            &variable_values,
+            &project_root_dir,
+            &existing_files,
+        );
+    }
+    smol::block_on(init_project(
+        template_info.id,
+        &args.name,
+        &variable_values,
+        &project_root_dir,
+        &existing_files,
+        |_| {},
+    ))?;
+    println!("Project at {} created!", project_root_dir.to_string_lossy());
+    Ok(())
+}
+
+/// Asks on stdin whether to overwrite each generated file that already exists.
+fn ask_existing_files(
+    template_id: &str,
+    project_name: &str,
+    variable_values: &VariableValues,
+    project_root_dir: &Path,
+) -> eyre::Result<ExistingFiles> {
+    if !is_nonempty_dir(project_root_dir) {
+        return Ok(ExistingFiles::Skip);
+    }
+    let replacer = ScaffyReplacer::new(project_name, variable_values);
+    let planned_files = plan_project(template_id, &replacer, variable_values)?;
+    let mut overwritten_paths = HashSet::new();
+    for existing_path in find_existing_files(&planned_files, project_root_dir) {
+        print!("Overwrite {}? [y/N] ", existing_path.display());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            overwritten_paths.insert(existing_path);
+        }
+    }
+    Ok(ExistingFiles::OverwriteOnly(overwritten_paths))
+}
+
+fn print_dry_run(
+    template_id: &str,
+    project_name: &str,
+    variable_values: &VariableValues,
+    project_root_dir: &Path,
+    existing_files: &ExistingFiles,
+) -> eyre::Result<()> {
+    let dry_run_entries = smol::block_on(dry_run_project(
+        template_id,
+        project_name,
+        variable_values,
+        project_root_dir,
+        existing_files,
+    ))?;
+    println!("{:<10} {:>9}  {:<6}  PATH", "ACTION", "BYTES", "INJECT");
+    for dry_run_entry in &dry_run_entries {
+        let output_path = dry_run_entry.output_path.display();
+        let path = match &dry_run_entry.kind {
+            PlannedEntryKind::File { .. } => output_path.to_string(),
+            PlannedEntryKind::EmptyFolder => format!("{output_path}/"),
+            PlannedEntryKind::Symlink { target } => {
+                format!("{output_path} -> {}", target.display())
+            }
+        };
+        println!(
+            "{:<10} {:>9}  {:<6}  {path}",
+            dry_run_entry.action.to_string(),
+            dry_run_entry
+                .byte_count
+                .map(|byte_count| byte_count.to_string())
+                .unwrap_or_else(|| "-".to_owned()),
+            if dry_run_entry.is_injected {
+                "yes"
+            } else {
+                "no"
+            },
+        );
+    }
+    let written_entries = dry_run_entries
+        .iter()
+        .filter(|dry_run_entry| dry_run_entry.action != PlannedAction::Skip);
+    println!(
+        "Dry run: {} entries totalling {} bytes would be written to {}; nothing was written.",
+        written_entries.clone().count(),
+        written_entries
+            .filter_map(|dry_run_entry| dry_run_entry.byte_count)
+            .sum::<usize>(),
+        project_root_dir.display()
+    );
+    if let ExistingFiles::Abort = existing_files
+        && is_nonempty_dir(project_root_dir)
+    {
+        println!(
+            "{} already exists and is not empty, so generating would be refused; pass --on-existing to merge into it.",
+            project_root_dir.display()
+        );
+    }
+    Ok(())
+}
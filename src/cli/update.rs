@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use color_eyre::eyre;
+
+use crate::{
+    snapshot::default_snapshots_dir, template_info::fetch_template_info, update::update_project,
+};
+
+#[derive(Args, Debug)]
+pub struct UpdateArgs {
+    /// Root of the project to update, containing its `.scaffy.toml`
+    #[arg(long, default_value = ".")]
+    pub dir: PathBuf,
+    /// New value of one of the template's variables, as NAME=VALUE; can be repeated, and other variables keep their recorded values
+    #[arg(long = "var", value_name = "NAME=VALUE")]
+    pub vars: Vec<String>,
+}
+
+pub fn run(args: UpdateArgs) -> eyre::Result<()> {
+    let mut answers = vec![];
+    for var in &args.vars {
+        let Some(answer) = var.split_once('=') else {
+            eyre::bail!("invalid --var {var}; use NAME=VALUE");
+        };
+        answers.push(answer);
+    }
+    let Some(snapshots_dir) = default_snapshots_dir() else {
+        eyre::bail!("could not find a data directory to cache template revisions in");
+    };
+    fetch_template_info()?;
+    let summary = update_project(&args.dir, &answers, &snapshots_dir)?;
+
+    if summary.old_template_hash == summary.new_template_hash {
+        println!(
+            "{} is already up to date with {}",
+            args.dir.display(),
+            summary.template_id
+        );
+    } else {
+        println!(
+            "Updated {} to the latest {} ({})",
+            args.dir.display(),
+            summary.template_id,
+            summary.new_template_hash
+        );
+    }
+    for (label, output_paths) in [
+        ("added", &summary.added),
+        ("updated", &summary.updated),
+        ("merged", &summary.merged),
+        ("removed", &summary.removed),
+    ] {
+        for output_path in output_paths {
+            println!("  {label:<9}{}", output_path.display());
+        }
+    }
+    for (output_path, reason) in &summary.conflicts {
+        println!("  {:<9}{}: {reason}", "conflict", output_path.display());
+    }
+    if !summary.conflicts.is_empty() {
+        eprintln!(
+            "{} path(s) need to be resolved by hand",
+            summary.conflicts.len()
+        );
+    }
+    Ok(())
+}
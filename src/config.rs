@@ -0,0 +1,36 @@
+use std::{fs, io, path::PathBuf};
+
+use color_eyre::eyre::{self, WrapErr};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    pub source: Option<String>,
+    #[serde(default)]
+    pub registries: Vec<RegistryConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryConfig {
+    pub name: String,
+    pub source: String,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("scaffy").join("config.toml"))
+}
+
+pub fn load_config() -> eyre::Result<Config> {
+    let Some(config_path) = config_path() else {
+        return Ok(Config::default());
+    };
+    match fs::read_to_string(&config_path) {
+        Ok(unparsed) => toml::from_str(&unparsed)
+            .wrap_err_with(|| format!("invalid config file {}", config_path.display())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err)
+            .wrap_err_with(|| format!("could not read config file {}", config_path.display())),
+    }
+}
@@ -0,0 +1,129 @@
+use color_eyre::eyre;
+use ratatui::{
+    crossterm::event::{Event, KeyCode},
+    prelude::*,
+    widgets::{self, Block, Clear, Wrap},
+};
+
+/// What the user chose to do about an [`ErrorDialog`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Try whatever failed again
+    Retry,
+    /// Dismiss the error and go back to where the tab can recover from it
+    Back,
+    Quit,
+}
+impl ErrorAction {
+    fn label(&self) -> &'static str {
+        match self {
+            ErrorAction::Retry => "Retry",
+            ErrorAction::Back => "Back",
+            ErrorAction::Quit => "Quit",
+        }
+    }
+    fn key(&self) -> char {
+        match self {
+            ErrorAction::Retry => 'r',
+            ErrorAction::Back => 'b',
+            ErrorAction::Quit => 'q',
+        }
+    }
+}
+
+/// A recoverable error shown over the current tab until one of its actions is picked
+pub struct ErrorDialog {
+    title: String,
+    message: String,
+    actions: Vec<ErrorAction>,
+    selected_idx: usize,
+}
+impl ErrorDialog {
+    pub fn new(title: impl Into<String>, err: &eyre::Report) -> Self {
+        ErrorDialog {
+            title: title.into(),
+            message: format!("{err:#}"),
+            actions: vec![ErrorAction::Back, ErrorAction::Quit],
+            selected_idx: 0,
+        }
+    }
+    /// Offers retrying, which is picked by default.
+    pub fn with_retry(mut self) -> Self {
+        self.actions.insert(0, ErrorAction::Retry);
+        self.selected_idx = 0;
+        self
+    }
+    /// Returns the action the user picked, if any.
+    pub fn handle_event(&mut self, ev: &Event) -> Option<ErrorAction> {
+        let Event::Key(key_ev) = ev else {
+            return None;
+        };
+        match key_ev.code {
+            KeyCode::Left | KeyCode::BackTab => {
+                self.selected_idx = self
+                    .selected_idx
+                    .checked_sub(1)
+                    .unwrap_or(self.actions.len() - 1);
+                None
+            }
+            KeyCode::Right | KeyCode::Tab => {
+                self.selected_idx = (self.selected_idx + 1) % self.actions.len();
+                None
+            }
+            KeyCode::Enter => Some(self.actions[self.selected_idx]),
+            KeyCode::Esc => Some(ErrorAction::Back),
+            KeyCode::Char(c) => self
+                .actions
+                .iter()
+                .find(|action| action.key() == c.to_ascii_lowercase())
+                .copied(),
+            _ => None,
+        }
+    }
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let width = (area.width * 3 / 5).max(40).min(area.width);
+        let message = widgets::Paragraph::new(self.message.as_str()).wrap(Wrap { trim: false });
+        let message_height = message.line_count(width.saturating_sub(2)) as u16;
+        let height = (message_height + 4).min(area.height);
+        let [dialog_area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(layout::Flex::Center)
+            .areas(area);
+        let [dialog_area] = Layout::vertical([Constraint::Length(height)])
+            .flex(layout::Flex::Center)
+            .areas(dialog_area);
+
+        Clear.render(dialog_area, buf);
+        let block = Block::bordered()
+            .title(format!(" {} ", self.title))
+            .border_style(Style::new().fg(Color::Red));
+        let [message_area, _, actions_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(block.inner(dialog_area));
+        block.render(dialog_area, buf);
+        message.render(message_area, buf);
+
+        let mut action_spans = vec![];
+        for (i, action) in self.actions.iter().enumerate() {
+            let style = if i == self.selected_idx {
+                Style::new().fg(Color::Black).bg(Color::LightYellow)
+            } else {
+                Style::new()
+            };
+            action_spans.push(Span::styled(
+                format!(
+                    " [{}] {} ",
+                    action.key().to_ascii_uppercase(),
+                    action.label()
+                ),
+                style,
+            ));
+            action_spans.push(Span::raw("  "));
+        }
+        Line::from(action_spans)
+            .centered()
+            .render(actions_area, buf);
+    }
+}
@@ -1,19 +1,47 @@
 pub mod app;
+pub mod cli;
+pub mod config;
+pub mod error_dialog;
 pub mod input_widget;
+pub mod markdown;
+pub mod progress_widget;
+pub mod provenance;
+pub mod registry;
+pub mod scaffold;
+pub mod snapshot;
 pub mod string_ops;
 pub mod tabs;
+pub mod template_index;
 pub mod template_info;
+pub mod template_source;
+pub mod template_vars;
+pub mod update;
 
+use clap::Parser;
 use color_eyre::Result;
 
-use crate::{app::App, template_info::fetch_template_info};
+use crate::{
+    app::App,
+    cli::{Cli, CliCommand},
+    config::load_config,
+    registry::{REGISTRIES, resolve_registries},
+};
 
 fn main() -> Result<()> {
+    let cli = Cli::parse();
     #[cfg(debug_assertions)]
     {
         simple_logging::log_to_file(".logs", log::LevelFilter::Info)?;
     }
     color_eyre::install()?;
+    // Indexing only reads a local templates directory, so a broken config or registry shouldn't stop it
+    if let Some(CliCommand::Index(index_args)) = cli.command {
+        return cli::index::run(index_args);
+    }
+    REGISTRIES.set(resolve_registries(&cli, &load_config()?)?);
+    if let Some(command) = cli.command {
+        return command.run();
+    }
     let eyre_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         ratatui::restore();
@@ -21,7 +49,6 @@ fn main() -> Result<()> {
         eprintln!("{}", panic_info);
     }));
     let terminal = ratatui::init();
-    fetch_template_info().unwrap_or_else(|err| panic!("{err}"));
     let mut app = App::new();
     let result = app.run(terminal);
     ratatui::restore();
@@ -0,0 +1,308 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::prelude::*;
+
+const CODE_STYLE: Style = Style::new().fg(Color::Yellow);
+const QUOTE_PREFIX: &str = "│ ";
+
+/// Renders markdown as styled text, with headings, emphasis, lists, quotes, links and code blocks.
+/// Tables and HTML aren't supported; HTML is left out and tables are shown as their raw text.
+pub fn markdown_to_text(markdown: &str) -> Text<'static> {
+    let mut renderer = MarkdownRenderer::default();
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(markdown, options) {
+        renderer.handle_event(event);
+    }
+    renderer.end_line();
+    while renderer.lines.last().is_some_and(|line| line.width() == 0) {
+        renderer.lines.pop();
+    }
+    Text::from(renderer.lines)
+}
+
+#[derive(Default)]
+struct MarkdownRenderer {
+    lines: Vec<Line<'static>>,
+    spans: Vec<Span<'static>>,
+    /// Styles of the inline elements the renderer is in, innermost last
+    styles: Vec<Style>,
+    /// The number of the next item of each list the renderer is in, or `None` for bullet lists
+    list_item_nums: Vec<Option<u64>>,
+    quote_depth: usize,
+    is_in_code_block: bool,
+    /// Destination of the link the renderer is in, shown after its text
+    link_url: Option<String>,
+}
+
+impl MarkdownRenderer {
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+    fn push_style(&mut self, patch: Style) {
+        self.styles.push(self.style().patch(patch));
+    }
+    fn push_span(&mut self, text: String, style: Style) {
+        if self.spans.is_empty() && self.quote_depth > 0 {
+            self.spans.push(Span::styled(
+                QUOTE_PREFIX.repeat(self.quote_depth),
+                Style::new().fg(Color::DarkGray),
+            ));
+        }
+        self.spans.push(Span::styled(text, style));
+    }
+    /// Finishes the current line, if anything was written on it
+    fn end_line(&mut self) {
+        if !self.spans.is_empty() {
+            self.lines.push(Line::from(std::mem::take(&mut self.spans)));
+        }
+    }
+    /// Separates blocks with an empty line, unless there already is one
+    fn end_block(&mut self) {
+        self.end_line();
+        if self.lines.last().is_some_and(|line| line.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start_tag(tag),
+            Event::End(tag_end) => self.end_tag(tag_end),
+            Event::Text(text) if self.is_in_code_block => {
+                for line in text.lines() {
+                    self.push_span(format!("  {line}"), CODE_STYLE);
+                    self.end_line();
+                }
+            }
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.push_span(text.into_string(), self.style());
+            }
+            Event::Code(code) => {
+                self.push_span(code.into_string(), self.style().patch(CODE_STYLE));
+            }
+            Event::SoftBreak => self.push_span(" ".to_owned(), self.style()),
+            Event::HardBreak => self.end_line(),
+            Event::Rule => {
+                self.end_line();
+                self.push_span("─".repeat(40), Style::new().fg(Color::DarkGray));
+                self.end_block();
+            }
+            Event::TaskListMarker(is_checked) => {
+                let marker = if is_checked { "[x] " } else { "[ ] " };
+                self.push_span(marker.to_owned(), self.style());
+            }
+            Event::FootnoteReference(label) => {
+                self.push_span(format!("[{label}]"), self.style());
+            }
+            Event::Html(_) | Event::InlineHtml(_) => {}
+        }
+    }
+    fn start_tag(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.end_block();
+                let mut style = Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+                if level == HeadingLevel::H1 {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                self.push_style(style);
+                self.push_span(format!("{} ", "#".repeat(level as usize)), self.style());
+            }
+            Tag::BlockQuote(_) => {
+                self.end_line();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.end_block();
+                self.is_in_code_block = true;
+            }
+            Tag::List(first_item_num) => {
+                self.end_line();
+                self.list_item_nums.push(first_item_num);
+            }
+            Tag::Item => {
+                self.end_line();
+                let indent = "  ".repeat(self.list_item_nums.len().saturating_sub(1));
+                let marker = match self.list_item_nums.last_mut() {
+                    Some(Some(item_num)) => {
+                        *item_num += 1;
+                        format!("{}. ", *item_num - 1)
+                    }
+                    _ => "• ".to_owned(),
+                };
+                self.push_span(format!("{indent}{marker}"), Style::new().fg(Color::Gray));
+            }
+            Tag::Emphasis => self.push_style(Style::new().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(Style::new().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.push_style(Style::new().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } => {
+                self.push_style(
+                    Style::new()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::UNDERLINED),
+                );
+                self.link_url = Some(dest_url.into_string());
+            }
+            Tag::Image { .. } => self.push_style(Style::new().add_modifier(Modifier::ITALIC)),
+            _ => {}
+        }
+    }
+    fn end_tag(&mut self, tag_end: TagEnd) {
+        match tag_end {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => {
+                if self.list_item_nums.is_empty() {
+                    self.end_block();
+                } else {
+                    self.end_line();
+                }
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.end_block();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.end_line();
+                self.quote_depth -= 1;
+                if self.quote_depth == 0 {
+                    self.end_block();
+                }
+            }
+            TagEnd::CodeBlock => {
+                self.is_in_code_block = false;
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.end_line();
+                self.list_item_nums.pop();
+                if self.list_item_nums.is_empty() {
+                    self.end_block();
+                }
+            }
+            TagEnd::Item => self.end_line(),
+            TagEnd::Link => {
+                self.styles.pop();
+                if let Some(link_url) = self.link_url.take()
+                    && !link_url.starts_with('#')
+                {
+                    self.push_span(format!(" ({link_url})"), Style::new().fg(Color::DarkGray));
+                }
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Image => {
+                self.styles.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::prelude::*;
+
+    use super::{CODE_STYLE, markdown_to_text};
+
+    /// The text of each rendered line
+    fn line_texts(markdown: &str) -> Vec<String> {
+        markdown_to_text(markdown)
+            .lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// The style of the first span with the given text
+    fn span_style(markdown: &str, span_text: &str) -> Style {
+        markdown_to_text(markdown)
+            .lines
+            .iter()
+            .flat_map(|line| &line.spans)
+            .find(|span| span.content == span_text)
+            .unwrap_or_else(|| panic!("no span \"{span_text}\""))
+            .style
+    }
+
+    #[test]
+    fn renders_headings_with_their_level() {
+        let markdown = "# Title\nIntro\n## Usage\n### Details";
+        assert_eq!(
+            line_texts(markdown),
+            ["# Title", "", "Intro", "", "## Usage", "", "### Details"]
+        );
+        let title_style = span_style(markdown, "Title");
+        assert!(
+            title_style
+                .add_modifier
+                .contains(Modifier::BOLD | Modifier::UNDERLINED)
+        );
+        let usage_style = span_style(markdown, "Usage");
+        assert!(usage_style.add_modifier.contains(Modifier::BOLD));
+        assert!(!usage_style.add_modifier.contains(Modifier::UNDERLINED));
+    }
+
+    #[test]
+    fn renders_nested_numbered_and_task_lists() {
+        assert_eq!(
+            line_texts(
+                "- one\n- two\n  - nested\n\n3. three\n4. four\n\n- [x] done\n- [ ] todo\n\nAfter"
+            ),
+            [
+                "• one",
+                "• two",
+                "  • nested",
+                "",
+                "3. three",
+                "4. four",
+                "",
+                "• [x] done",
+                "• [ ] todo",
+                "",
+                "After"
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_code_blocks_indented_line_by_line() {
+        let markdown = "Run:\n\n```sh\ncargo build\ncargo test\n```\nDone";
+        assert_eq!(
+            line_texts(markdown),
+            ["Run:", "", "  cargo build", "  cargo test", "", "Done"]
+        );
+        assert_eq!(span_style(markdown, "  cargo build"), CODE_STYLE);
+    }
+
+    #[test]
+    fn prefixes_block_quotes_by_depth() {
+        assert_eq!(
+            line_texts("> quoted\n>\n> > nested\n\nAfter"),
+            ["│ quoted", "", "│ │ nested", "", "After"]
+        );
+    }
+
+    #[test]
+    fn styles_inline_elements() {
+        let markdown =
+            "*it* **bold** ~~gone~~ `code` [site](https://example.com) [local](#usage) <b>html</b>";
+        assert_eq!(
+            line_texts(markdown),
+            ["it bold gone code site (https://example.com) local html"]
+        );
+        assert!(
+            span_style(markdown, "it")
+                .add_modifier
+                .contains(Modifier::ITALIC)
+        );
+        assert!(
+            span_style(markdown, "bold")
+                .add_modifier
+                .contains(Modifier::BOLD)
+        );
+        assert!(
+            span_style(markdown, "gone")
+                .add_modifier
+                .contains(Modifier::CROSSED_OUT)
+        );
+        assert_eq!(span_style(markdown, "code"), CODE_STYLE);
+        assert_eq!(span_style(markdown, "site").fg, Some(Color::LightBlue));
+        // Emphasis ends with its element
+        assert_eq!(span_style(markdown, " "), Style::default());
+    }
+}
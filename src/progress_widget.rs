@@ -0,0 +1,34 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::{prelude::*, widgets::Gauge};
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_FRAME_MILLIS: u128 = 80;
+
+/// A spinner followed by `label`, animated by the time it's drawn at
+pub fn spinner_line(label: &str) -> Line<'_> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let spinner_frame =
+        SPINNER_FRAMES[(millis / SPINNER_FRAME_MILLIS) as usize % SPINNER_FRAMES.len()];
+    Line::from(vec![
+        Span::styled(spinner_frame, Style::new().fg(Color::Yellow)),
+        Span::raw(" "),
+        Span::raw(label),
+    ])
+}
+
+/// A one line progress bar showing `done` out of `total`
+pub fn progress_bar(label: &str, done: usize, total: usize) -> Gauge<'static> {
+    let ratio = if total == 0 {
+        0.0
+    } else {
+        (done as f64 / total as f64).min(1.0)
+    };
+    Gauge::default()
+        .gauge_style(Style::new().fg(Color::Yellow).bg(Color::DarkGray))
+        .ratio(ratio)
+        .label(format!("{label} {done} / {total}"))
+}
@@ -0,0 +1,74 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{self, WrapErr};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{template_info::ArcStr, template_vars::VariableValues};
+
+/// Name of the manifest written into the root of every generated project
+pub const PROVENANCE_FILE_NAME: &str = ".scaffy.toml";
+
+/// Records what a project was generated from, so it can be audited and later updated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// Version of scaffy that generated the project
+    pub scaffy_version: ArcStr,
+    /// Name of the registry the template came from
+    pub registry: ArcStr,
+    /// The template's `path` within its registry
+    pub template_path: ArcStr,
+    /// [`template_hash`] of the template files the project was generated from
+    pub template_hash: ArcStr,
+    /// A source that serves the template files as they were hashed, if the registry could be pinned
+    /// to their revision; see `TemplateSource::pinned_source`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_source: Option<ArcStr>,
+    pub project_name: ArcStr,
+    #[serde(default)]
+    pub variables: VariableValues,
+}
+impl Provenance {
+    pub fn to_toml(&self) -> eyre::Result<String> {
+        Ok(format!(
+            "# Written by scaffy when this project was generated; used by `scaffy update`\n{}",
+            toml::to_string(self)?
+        ))
+    }
+    pub fn read(project_root_dir: &Path) -> eyre::Result<Self> {
+        let provenance_path = project_root_dir.join(PROVENANCE_FILE_NAME);
+        let unparsed = fs::read_to_string(&provenance_path).wrap_err_with(|| {
+            format!(
+                "could not read {}; was this project generated by scaffy?",
+                provenance_path.display()
+            )
+        })?;
+        toml::from_str(&unparsed).wrap_err_with(|| format!("invalid {}", provenance_path.display()))
+    }
+}
+
+/// Hashes a template's structure index together with the contents of its files, given as
+/// `/`-separated paths within the template, so any change to the template changes the hash.
+pub fn template_hash<'a>(
+    serialized_structure: &str,
+    files: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+) -> String {
+    let mut files = files.into_iter().collect::<Vec<_>>();
+    files.sort_unstable_by_key(|(file_path, _)| *file_path);
+    let mut hasher = Sha256::new();
+    hasher.update(serialized_structure.as_bytes());
+    for (file_path, file_contents) in files {
+        hasher.update([0]);
+        hasher.update(file_path.as_bytes());
+        hasher.update([0]);
+        hasher.update((file_contents.len() as u64).to_le_bytes());
+        hasher.update(file_contents);
+    }
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("sha256:{hash}")
+}
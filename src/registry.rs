@@ -0,0 +1,143 @@
+use std::{cell::RefCell, sync::Arc};
+
+use color_eyre::eyre;
+
+use crate::{
+    cli::Cli,
+    config::Config,
+    template_info::ArcStr,
+    template_source::{TemplateSource, default_template_source, parse_template_source},
+};
+
+/// The name of the registry configured through `--source`, `SCAFFY_SOURCE` or the `source` config key.
+pub const DEFAULT_REGISTRY_NAME: &str = "scaffy";
+
+/// A named template source. Templates from registries later in [`REGISTRIES`] get
+/// namespaced under the registry name when their `path` clashes with an earlier one.
+pub struct Registry {
+    pub name: ArcStr,
+    pub source: Arc<dyn TemplateSource>,
+}
+impl Registry {
+    pub fn new(name: &str, source: Arc<dyn TemplateSource>) -> eyre::Result<Self> {
+        if name.is_empty() || name.contains('/') {
+            eyre::bail!(
+                "invalid registry name \"{name}\"; names must be nonempty and not contain '/'"
+            );
+        }
+        Ok(Registry {
+            name: name.into(),
+            source,
+        })
+    }
+}
+
+thread_local! {
+pub static REGISTRIES: RefCell<Vec<Registry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Environment variable that sets the source of the default registry
+pub const SOURCE_ENV_VAR: &str = "SCAFFY_SOURCE";
+
+/// The source of the default registry: `--source`, then `env_source` (the value of [`SOURCE_ENV_VAR`]), then the
+/// `source` config key. `None` means templates come from [`default_template_source`].
+pub fn default_registry_source<'a>(
+    cli: &'a Cli,
+    env_source: Option<&'a str>,
+    config: &'a Config,
+) -> Option<&'a str> {
+    cli.source
+        .as_deref()
+        .or(env_source.filter(|source| !source.is_empty()))
+        .or(config.source.as_deref())
+}
+
+pub fn resolve_registries(cli: &Cli, config: &Config) -> eyre::Result<Vec<Registry>> {
+    let env_source = std::env::var(SOURCE_ENV_VAR).ok();
+    let default_source = match default_registry_source(cli, env_source.as_deref(), config) {
+        Some(source) => parse_template_source(source),
+        None => default_template_source(),
+    };
+    let mut registries = vec![Registry::new(DEFAULT_REGISTRY_NAME, default_source)?];
+    let extra_registries = config
+        .registries
+        .iter()
+        .map(|registry_config| {
+            (
+                registry_config.name.as_str(),
+                registry_config.source.as_str(),
+            )
+        })
+        .chain(cli.registries.iter().map(|registry_arg| {
+            registry_arg
+                .split_once('=')
+                .unwrap_or((registry_arg.as_str(), ""))
+        }));
+    for (name, source) in extra_registries {
+        if source.is_empty() {
+            eyre::bail!("registry \"{name}\" has no source; use NAME=SOURCE");
+        }
+        if registries.iter().any(|registry| &*registry.name == name) {
+            eyre::bail!("registry \"{name}\" is configured more than once");
+        }
+        registries.push(Registry::new(name, parse_template_source(source))?);
+    }
+    Ok(registries)
+}
+
+/// Names of the registries that could not be reached and are being served from the offline cache
+pub fn stale_registry_names() -> Vec<ArcStr> {
+    REGISTRIES.with_borrow(|registries| {
+        registries
+            .iter()
+            .filter(|registry| registry.source.is_stale())
+            .map(|registry| registry.name.clone())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::default_registry_source;
+    use crate::{cli::Cli, config::Config};
+
+    fn selected_source(
+        args: &[&str],
+        env_source: Option<&str>,
+        config_source: Option<&str>,
+    ) -> Option<String> {
+        let cli = Cli::try_parse_from(["scaffy"].iter().chain(args)).unwrap();
+        let config = Config {
+            source: config_source.map(String::from),
+            ..Config::default()
+        };
+        default_registry_source(&cli, env_source, &config).map(String::from)
+    }
+
+    #[test]
+    fn prefers_flag_then_env_then_config() {
+        assert_eq!(selected_source(&[], None, None), None);
+        assert_eq!(
+            selected_source(&[], None, Some("./config")).as_deref(),
+            Some("./config")
+        );
+        assert_eq!(
+            selected_source(&[], Some("./env"), Some("./config")).as_deref(),
+            Some("./env")
+        );
+        assert_eq!(
+            selected_source(&[], Some(""), Some("./config")).as_deref(),
+            Some("./config")
+        );
+        assert_eq!(
+            selected_source(&["--source", "./flag"], Some("./env"), Some("./config")).as_deref(),
+            Some("./flag")
+        );
+        assert_eq!(
+            selected_source(&["list", "--source", "./flag"], Some("./env"), None).as_deref(),
+            Some("./flag")
+        );
+    }
+}
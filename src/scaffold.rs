@@ -0,0 +1,1179 @@
+use std::{
+    collections::HashSet,
+    fmt, io,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{self, WrapErr};
+use futures::future::join_all;
+use smol::fs;
+use tempfile::TempDir;
+
+use crate::{
+    provenance::{PROVENANCE_FILE_NAME, Provenance, template_hash},
+    snapshot::{Snapshot, default_snapshots_dir},
+    string_ops::ScaffyReplacer,
+    template_index::serialize_template_structure,
+    template_info::{
+        ArcStr, FileMode, TemplateStructure, TemplateStructureDirEntryData,
+        filter_template_structure, get_template_file_contents, get_template_info,
+        get_template_structure, load_template_structure, pin_template_source,
+    },
+    template_vars::VariableValues,
+};
+
+/// A file, empty folder or symlink that generating a project will create
+#[derive(Clone, Debug)]
+pub struct PlannedEntry {
+    /// Path of the generated entry relative to the project root, with placeholders substituted
+    pub output_path: PathBuf,
+    pub kind: PlannedEntryKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum PlannedEntryKind {
+    File {
+        /// `/`-separated path of the file within the template
+        template_file_path: ArcStr,
+        /// Whether placeholders in the file's contents are replaced; only done if the file is valid UTF-8
+        inject_project_info: bool,
+        mode: Option<FileMode>,
+    },
+    EmptyFolder,
+    Symlink {
+        /// Target relative to the folder containing the link, with placeholders substituted
+        target: PathBuf,
+    },
+}
+
+/// Lists every entry the template with id `template_id` generates for the given variables.
+pub fn plan_project(
+    template_id: &str,
+    replacer: &ScaffyReplacer,
+    variable_values: &VariableValues,
+) -> eyre::Result<Vec<PlannedEntry>> {
+    plan_template_structure(
+        &get_template_structure(template_id.into())?,
+        replacer,
+        variable_values,
+    )
+}
+
+/// Lists every entry a template with the given structure generates for the given variables.
+pub fn plan_template_structure(
+    template_structure: &TemplateStructure,
+    replacer: &ScaffyReplacer,
+    variable_values: &VariableValues,
+) -> eyre::Result<Vec<PlannedEntry>> {
+    let template_structure = filter_template_structure(template_structure, variable_values)?;
+    let mut stack: Vec<(ArcStr, TemplateStructureDirEntryData, Vec<ArcStr>)> = template_structure
+        .into_iter()
+        .map(|(dir_entry_name, dir_entry)| (dir_entry_name, dir_entry, vec![]))
+        .collect();
+
+    let mut planned_entries: Vec<PlannedEntry> = vec![];
+    while let Some((dir_entry_name, dir_entry, parent_path)) = stack.pop() {
+        let output_path = resolve_output_path(&parent_path, &dir_entry_name, replacer)?;
+        match dir_entry {
+            TemplateStructureDirEntryData::Folder { children, .. } => {
+                stack.extend(
+                    children
+                        .into_iter()
+                        .map(|(sub_dir_entry_name, sub_dir_entry)| {
+                            let mut sub_dir_parent_path = parent_path.clone();
+                            sub_dir_parent_path.push(dir_entry_name.clone());
+                            (sub_dir_entry_name, sub_dir_entry, sub_dir_parent_path)
+                        }),
+                );
+            }
+            TemplateStructureDirEntryData::File {
+                inject_project_info,
+                mode,
+                ..
+            } => {
+                let mut template_path_parts = parent_path;
+                template_path_parts.push(dir_entry_name);
+                planned_entries.push(PlannedEntry {
+                    output_path,
+                    kind: PlannedEntryKind::File {
+                        template_file_path: template_path_parts.join("/").into(),
+                        inject_project_info,
+                        mode,
+                    },
+                });
+            }
+            TemplateStructureDirEntryData::EmptyFolder { .. } => {
+                planned_entries.push(PlannedEntry {
+                    output_path,
+                    kind: PlannedEntryKind::EmptyFolder,
+                });
+            }
+            TemplateStructureDirEntryData::Symlink { target, .. } => {
+                let target = PathBuf::from(replacer.replace(&target));
+                if symlink_escapes_root(&output_path, &target) {
+                    eyre::bail!(
+                        "the symlink {} points to {}, which is outside of the project",
+                        output_path.display(),
+                        target.display()
+                    );
+                }
+                planned_entries.push(PlannedEntry {
+                    output_path,
+                    kind: PlannedEntryKind::Symlink { target },
+                });
+            }
+        }
+    }
+    let symlink_paths: HashSet<&Path> = planned_entries
+        .iter()
+        .filter(|planned_entry| matches!(planned_entry.kind, PlannedEntryKind::Symlink { .. }))
+        .map(|planned_entry| &*planned_entry.output_path)
+        .collect();
+    for planned_entry in &planned_entries {
+        if let PlannedEntryKind::Symlink { target } = &planned_entry.kind
+            && symlink_passes_through_symlink(&planned_entry.output_path, target, &symlink_paths)
+        {
+            eyre::bail!(
+                "the symlink {} points to {}, which goes through another symlink of the template; only targets that don't are allowed",
+                planned_entry.output_path.display(),
+                target.display()
+            );
+        }
+    }
+    Ok(planned_entries)
+}
+
+/// Substitutes the placeholders in a template entry's path, failing if the entry's name or any
+/// substituted part isn't a plain file name, since it could then end up outside of the project root.
+fn resolve_output_path(
+    parent_path: &[ArcStr],
+    dir_entry_name: &str,
+    replacer: &ScaffyReplacer,
+) -> eyre::Result<PathBuf> {
+    let template_entry_path = || {
+        parent_path
+            .iter()
+            .map(|path_part| &**path_part)
+            .chain([dir_entry_name])
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    let mut output_path = PathBuf::new();
+    for path_part in parent_path
+        .iter()
+        .map(|path_part| &**path_part)
+        .chain([dir_entry_name])
+    {
+        if !is_plain_path_part(path_part) {
+            eyre::bail!(
+                "the template entry \"{}\" has the invalid name \"{path_part}\"; names can't be empty, \".\" or \"..\", or contain path separators",
+                template_entry_path()
+            );
+        }
+        let replaced_path_part = replacer.replace(path_part);
+        if !is_plain_path_part(&replaced_path_part) {
+            eyre::bail!(
+                "the template entry \"{}\" would be named \"{replaced_path_part}\", which could place it outside of the project; check the project name and variables for \"..\" or path separators",
+                template_entry_path()
+            );
+        }
+        output_path.push(replaced_path_part);
+    }
+    Ok(output_path)
+}
+
+/// Whether `path_part` names an entry directly inside a folder, rather than the folder itself, its parent or an absolute path
+pub fn is_plain_path_part(path_part: &str) -> bool {
+    !path_part.contains(['/', '\\', '\0'])
+        && matches!(
+            Path::new(path_part).components().collect::<Vec<_>>()[..],
+            [Component::Normal(_)]
+        )
+}
+
+/// Whether a symlink at `link_path` with the relative `target` would point outside of the root
+/// that `link_path` is relative to. Absolute targets always count as escaping.
+pub fn symlink_escapes_root(link_path: &Path, target: &Path) -> bool {
+    let mut depth = link_path.components().count() as isize - 1;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                depth -= 1;
+                if depth < 0 {
+                    return true;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return true,
+        }
+    }
+    false
+}
+
+/// Whether the relative `target` of a symlink at `link_path` goes through one of `symlink_paths` on the way,
+/// e.g. `esc -> sub/up/..` next to `sub/up -> ..`. [`symlink_escapes_root`] only follows the target's text,
+/// so such a chain could point outside of the root even though each of its links looks like it stays inside.
+pub fn symlink_passes_through_symlink(
+    link_path: &Path,
+    target: &Path,
+    symlink_paths: &HashSet<&Path>,
+) -> bool {
+    let mut path = link_path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut components = target.components().peekable();
+    while let Some(component) = components.next() {
+        match component {
+            Component::Normal(name) => {
+                path.push(name);
+                if components.peek().is_some() && symlink_paths.contains(&*path) {
+                    return true;
+                }
+            }
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    false
+}
+
+/// How to handle a target directory that already has files in it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExistingFilesMode {
+    /// Refuse to generate into a nonempty directory
+    #[default]
+    Abort,
+    /// Keep existing files and only write the ones that don't exist yet
+    Skip,
+    /// Replace existing files with the template's
+    Overwrite,
+    /// Decide for each existing file whether to overwrite it
+    Ask,
+}
+
+/// What to do with the generated files that already exist in the target directory
+#[derive(Clone, Debug)]
+pub enum ExistingFiles {
+    Abort,
+    Skip,
+    Overwrite,
+    /// Overwrite only these paths, relative to the project root, and skip the others
+    OverwriteOnly(HashSet<PathBuf>),
+}
+impl ExistingFiles {
+    /// What generating a project into `project_root_dir` does with the entry at `output_path`
+    pub fn planned_action(&self, project_root_dir: &Path, output_path: &Path) -> PlannedAction {
+        if !self.should_write(project_root_dir, output_path) {
+            PlannedAction::Skip
+        } else if is_present(&project_root_dir.join(output_path)) {
+            PlannedAction::Overwrite
+        } else {
+            PlannedAction::Create
+        }
+    }
+    fn should_write(&self, project_root_dir: &Path, output_path: &Path) -> bool {
+        match self {
+            ExistingFiles::Abort | ExistingFiles::Overwrite => true,
+            ExistingFiles::Skip => !is_present(&project_root_dir.join(output_path)),
+            ExistingFiles::OverwriteOnly(overwritten_paths) => {
+                overwritten_paths.contains(output_path)
+                    || !is_present(&project_root_dir.join(output_path))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlannedAction {
+    Create,
+    Overwrite,
+    Skip,
+}
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PlannedAction::Create => "create",
+            PlannedAction::Overwrite => "overwrite",
+            PlannedAction::Skip => "skip",
+        })
+    }
+}
+
+/// An entry generating a project would create, as reported by [`dry_run_project`]
+#[derive(Clone, Debug)]
+pub struct DryRunEntry {
+    pub output_path: PathBuf,
+    pub kind: PlannedEntryKind,
+    /// Size of the file after placeholders are replaced
+    pub byte_count: Option<usize>,
+    /// Whether placeholders are replaced in the file, which is only done for text files flagged `inject_project_info`
+    pub is_injected: bool,
+    pub action: PlannedAction,
+}
+
+/// Renders every entry [`init_project`] would create without writing anything, sorted by output path.
+pub async fn dry_run_project(
+    template_id: &str,
+    project_name: &str,
+    variable_values: &VariableValues,
+    project_root_dir: &Path,
+    existing_files: &ExistingFiles,
+) -> eyre::Result<Vec<DryRunEntry>> {
+    let replacer = ScaffyReplacer::new(project_name, variable_values);
+    let replacer = &replacer;
+    let template_structure = load_template_structure(template_id.into()).await?;
+    let planned_entries: Vec<PlannedEntry> =
+        plan_template_structure(&template_structure, replacer, variable_values)?
+            .into_iter()
+            .filter(|planned_entry| !is_already_created(planned_entry, project_root_dir))
+            .collect();
+    let rendered_sizes = join_all(planned_entries.iter().map(|planned_entry| async move {
+        let PlannedEntryKind::File {
+            template_file_path,
+            inject_project_info,
+            ..
+        } = &planned_entry.kind
+        else {
+            return Ok((None, false, None));
+        };
+        let template_file_contents =
+            get_template_file_contents(template_id, template_file_path).await?;
+        let is_injected = *inject_project_info && str::from_utf8(&template_file_contents).is_ok();
+        let file_contents = render_file_contents(
+            template_file_contents.clone(),
+            *inject_project_info,
+            replacer,
+        );
+        Ok::<_, eyre::Error>((
+            Some(file_contents.len()),
+            is_injected,
+            Some((&**template_file_path, template_file_contents)),
+        ))
+    }))
+    .await;
+    let mut dry_run_entries = vec![];
+    let mut failed_files = vec![];
+    let mut template_files = vec![];
+    for (planned_entry, rendered_size) in planned_entries.iter().zip(rendered_sizes) {
+        match rendered_size {
+            Ok((byte_count, is_injected, template_file)) => {
+                template_files.extend(template_file);
+                dry_run_entries.push(DryRunEntry {
+                    action: existing_files
+                        .planned_action(project_root_dir, &planned_entry.output_path),
+                    output_path: planned_entry.output_path.clone(),
+                    kind: planned_entry.kind.clone(),
+                    byte_count,
+                    is_injected,
+                });
+            }
+            Err(error) => failed_files.push(FailedFile {
+                output_path: planned_entry.output_path.clone(),
+                error,
+            }),
+        }
+    }
+    if !failed_files.is_empty() {
+        return Err(GenerationError { failed_files }.into());
+    }
+    // The manifest is always written, even over an existing one
+    let provenance = build_provenance(
+        template_id,
+        pin_template_source(template_id).await,
+        &template_structure,
+        project_name,
+        variable_values,
+        &template_files,
+    )?;
+    let provenance_path = PathBuf::from(PROVENANCE_FILE_NAME);
+    dry_run_entries.retain(|dry_run_entry| dry_run_entry.output_path != provenance_path);
+    dry_run_entries.push(DryRunEntry {
+        action: if is_present(&project_root_dir.join(&provenance_path)) {
+            PlannedAction::Overwrite
+        } else {
+            PlannedAction::Create
+        },
+        output_path: provenance_path,
+        kind: PlannedEntryKind::File {
+            template_file_path: PROVENANCE_FILE_NAME.into(),
+            inject_project_info: false,
+            mode: None,
+        },
+        byte_count: Some(provenance.to_toml()?.len()),
+        is_injected: false,
+    });
+    dry_run_entries.sort_by(|entry_1, entry_2| entry_1.output_path.cmp(&entry_2.output_path));
+    Ok(dry_run_entries)
+}
+
+pub fn is_nonempty_dir(path: &Path) -> bool {
+    std::fs::read_dir(path).is_ok_and(|mut dir_entries| dir_entries.next().is_some())
+}
+
+/// Whether something exists at `path`, counting symlinks whose target doesn't exist
+fn is_present(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/// Whether creating `planned_entry` under `project_root_dir` would be a no-op, i.e. it's a folder that already exists
+fn is_already_created(planned_entry: &PlannedEntry, project_root_dir: &Path) -> bool {
+    matches!(planned_entry.kind, PlannedEntryKind::EmptyFolder)
+        && project_root_dir.join(&planned_entry.output_path).is_dir()
+}
+
+/// The output paths of `planned_entries` that already exist under `project_root_dir`, sorted
+pub fn find_existing_files(
+    planned_entries: &[PlannedEntry],
+    project_root_dir: &Path,
+) -> Vec<PathBuf> {
+    let mut existing_paths: Vec<PathBuf> = planned_entries
+        .iter()
+        .filter(|planned_entry| {
+            is_present(&project_root_dir.join(&planned_entry.output_path))
+                && !is_already_created(planned_entry, project_root_dir)
+        })
+        .map(|planned_entry| planned_entry.output_path.clone())
+        .collect();
+    existing_paths.sort();
+    existing_paths
+}
+
+/// A generated file that could not be written, and why
+#[derive(Debug)]
+pub struct FailedFile {
+    /// Path of the file relative to the project root
+    pub output_path: PathBuf,
+    pub error: eyre::Report,
+}
+
+/// Returned by [`init_project`] when some files could not be generated; nothing is left behind in that case
+#[derive(Debug)]
+pub struct GenerationError {
+    pub failed_files: Vec<FailedFile>,
+}
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not generate {} file(s), so no changes were made:",
+            self.failed_files.len()
+        )?;
+        for failed_file in &self.failed_files {
+            write!(
+                f,
+                "\n  {}: {:#}",
+                failed_file.output_path.display(),
+                failed_file.error
+            )?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for GenerationError {}
+
+/// An entry [`init_project`] generates, as listed by [`GenerationProgress::Planned`]
+#[derive(Clone, Debug)]
+pub struct GenerationEntry {
+    pub output_path: PathBuf,
+    /// Whether the entry is a file, which is downloaded before it's written
+    pub is_file: bool,
+    /// False for entries that already exist and are kept
+    pub should_write: bool,
+}
+
+/// How far [`init_project`] got, reported as it goes. Entries are referred to by their index in
+/// [`GenerationProgress::Planned`]'s list.
+#[derive(Clone, Debug)]
+pub enum GenerationProgress {
+    /// The template's structure was loaded, and these entries will be generated
+    Planned {
+        entries: Vec<GenerationEntry>,
+    },
+    Downloaded {
+        entry_idx: usize,
+    },
+    Written {
+        entry_idx: usize,
+    },
+    /// The entry already exists and is kept; files are still downloaded for the provenance hash
+    Skipped {
+        entry_idx: usize,
+    },
+    Failed {
+        entry_idx: usize,
+        error: String,
+    },
+    /// Every entry was written, and the project is being moved into place
+    Finishing,
+}
+
+/// Writes the template with id `template_id` into `project_root_dir`, substituting `project_name`
+/// and `variable_values` into its placeholders.
+///
+/// Every file is first written to a staging directory next to `project_root_dir`, which is only
+/// moved into place once all of them succeed, so a failure leaves `project_root_dir` untouched.
+pub async fn init_project(
+    template_id: ArcStr,
+    project_name: &str,
+    variable_values: &VariableValues,
+    project_root_dir: &Path,
+    existing_files: &ExistingFiles,
+    on_progress: impl Fn(GenerationProgress),
+) -> eyre::Result<()> {
+    let mut project_generation = ProjectGeneration::plan(
+        template_id,
+        project_name,
+        variable_values,
+        project_root_dir,
+        existing_files,
+        &on_progress,
+    )
+    .await?;
+    project_generation
+        .write_entries(&on_progress, &GenerationCancellation::default())
+        .await?;
+    project_generation.finish(&on_progress)
+}
+
+/// Stops a [`ProjectGeneration`] from writing any more entries. Writes already underway when it's
+/// cancelled still finish, so nothing is written to the staging directory after it's deleted.
+#[derive(Clone, Debug, Default)]
+pub struct GenerationCancellation(Arc<AtomicBool>);
+impl GenerationCancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    fn check(&self) -> eyre::Result<()> {
+        if self.is_cancelled() {
+            eyre::bail!("the generation was cancelled");
+        }
+        Ok(())
+    }
+}
+
+/// The steps of [`init_project`], split up so that the entries which failed can be written again
+/// without starting over. Dropping it before [`ProjectGeneration::finish`] deletes everything staged.
+pub struct ProjectGeneration {
+    template_id: ArcStr,
+    project_name: String,
+    variable_values: VariableValues,
+    project_root_dir: PathBuf,
+    is_target_nonempty: bool,
+    pinned_source: Option<ArcStr>,
+    template_structure: TemplateStructure,
+    planned_entries: Vec<PlannedEntry>,
+    should_write: Vec<bool>,
+    staging_dir: TempDir,
+    staged_root_dir: PathBuf,
+    /// Whether each entry was written to the staging directory along with its template file's
+    /// contents, or `None` if it wasn't generated yet
+    entry_results: Vec<Option<(bool, Option<Vec<u8>>)>>,
+}
+impl ProjectGeneration {
+    /// Loads the template's structure, works out which entries to write and creates the staging directory.
+    pub async fn plan(
+        template_id: ArcStr,
+        project_name: &str,
+        variable_values: &VariableValues,
+        project_root_dir: &Path,
+        existing_files: &ExistingFiles,
+        on_progress: &impl Fn(GenerationProgress),
+    ) -> eyre::Result<Self> {
+        let is_target_nonempty = is_nonempty_dir(project_root_dir);
+        if let ExistingFiles::Abort = existing_files
+            && is_target_nonempty
+        {
+            eyre::bail!(
+                "{} already exists and is not empty",
+                project_root_dir.display()
+            );
+        }
+        let replacer = ScaffyReplacer::new(project_name, variable_values);
+        // Pinned before anything is fetched, so the recorded revision can't be older than the fetched files
+        let pinned_source = pin_template_source(&template_id).await;
+        let template_structure = load_template_structure(template_id.clone()).await?;
+        let planned_entries: Vec<PlannedEntry> =
+            plan_template_structure(&template_structure, &replacer, variable_values)?
+                .into_iter()
+                .filter(|planned_entry| !is_already_created(planned_entry, project_root_dir))
+                .collect();
+        let should_write: Vec<bool> = planned_entries
+            .iter()
+            .map(|planned_entry| {
+                existing_files.should_write(project_root_dir, &planned_entry.output_path)
+            })
+            .collect();
+
+        let staging_dir = create_staging_dir(project_root_dir)?;
+        let staged_root_dir = staging_dir.path().join("project");
+        std::fs::create_dir(&staged_root_dir)?;
+
+        on_progress(GenerationProgress::Planned {
+            entries: planned_entries
+                .iter()
+                .zip(&should_write)
+                .map(|(planned_entry, should_write)| GenerationEntry {
+                    output_path: planned_entry.output_path.clone(),
+                    is_file: matches!(planned_entry.kind, PlannedEntryKind::File { .. }),
+                    should_write: *should_write,
+                })
+                .collect(),
+        });
+        Ok(ProjectGeneration {
+            template_id,
+            project_name: project_name.to_owned(),
+            variable_values: variable_values.clone(),
+            project_root_dir: project_root_dir.to_owned(),
+            is_target_nonempty,
+            pinned_source,
+            template_structure,
+            entry_results: vec![None; planned_entries.len()],
+            planned_entries,
+            should_write,
+            staging_dir,
+            staged_root_dir,
+        })
+    }
+
+    /// Writes every entry that wasn't generated yet to the staging directory, so calling this
+    /// again after it fails only retries the entries that failed. Once `cancellation` is cancelled,
+    /// the entries that weren't started fail, and this returns when the ones underway are done.
+    pub async fn write_entries(
+        &mut self,
+        on_progress: &impl Fn(GenerationProgress),
+        cancellation: &GenerationCancellation,
+    ) -> Result<(), GenerationError> {
+        let replacer = ScaffyReplacer::new(&self.project_name, &self.variable_values);
+        let pending_entry_idxs: Vec<usize> = (0..self.planned_entries.len())
+            .filter(|entry_idx| self.entry_results[*entry_idx].is_none())
+            .collect();
+        let results =
+            join_all(pending_entry_idxs.iter().map(|entry_idx| {
+                self.write_entry(*entry_idx, &replacer, on_progress, cancellation)
+            }))
+            .await;
+        let mut failed_files = vec![];
+        for (entry_idx, result) in pending_entry_idxs.into_iter().zip(results) {
+            match result {
+                Ok(entry_result) => self.entry_results[entry_idx] = Some(entry_result),
+                Err(error) => {
+                    on_progress(GenerationProgress::Failed {
+                        entry_idx,
+                        error: format!("{error:#}"),
+                    });
+                    failed_files.push(FailedFile {
+                        output_path: self.planned_entries[entry_idx].output_path.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+        if !failed_files.is_empty() {
+            return Err(GenerationError { failed_files });
+        }
+        Ok(())
+    }
+
+    async fn write_entry(
+        &self,
+        entry_idx: usize,
+        replacer: &ScaffyReplacer,
+        on_progress: &impl Fn(GenerationProgress),
+        cancellation: &GenerationCancellation,
+    ) -> eyre::Result<(bool, Option<Vec<u8>>)> {
+        let planned_entry = &self.planned_entries[entry_idx];
+        // Skipped files are still fetched, since the provenance hash covers every file of the template
+        let template_file_contents = match &planned_entry.kind {
+            PlannedEntryKind::File {
+                template_file_path, ..
+            } => {
+                let template_file_contents =
+                    get_template_file_contents(&self.template_id, template_file_path).await?;
+                on_progress(GenerationProgress::Downloaded { entry_idx });
+                Some(template_file_contents)
+            }
+            _ => None,
+        };
+        if !self.should_write[entry_idx] {
+            on_progress(GenerationProgress::Skipped { entry_idx });
+            return Ok((false, template_file_contents));
+        }
+        let entry_path = self.staged_root_dir.join(&planned_entry.output_path);
+        cancellation.check()?;
+        if let Some(entry_parent_path) = entry_path.parent() {
+            fs::create_dir_all(entry_parent_path).await?;
+        }
+        cancellation.check()?;
+        match &planned_entry.kind {
+            PlannedEntryKind::File {
+                inject_project_info,
+                mode,
+                ..
+            } => {
+                let file_contents = render_file_contents(
+                    template_file_contents.clone().unwrap_or_default(),
+                    *inject_project_info,
+                    replacer,
+                );
+                fs::write(&entry_path, file_contents).await?;
+                if let Some(mode) = mode {
+                    set_file_mode(&entry_path, *mode).await?;
+                }
+            }
+            PlannedEntryKind::EmptyFolder => {
+                fs::create_dir_all(&entry_path).await?;
+            }
+            PlannedEntryKind::Symlink { target } => {
+                create_symlink(target, &entry_path).await?;
+            }
+        }
+        on_progress(GenerationProgress::Written { entry_idx });
+        Ok((true, template_file_contents))
+    }
+
+    /// Writes the provenance manifest and moves the staged project into place, once every entry was written.
+    pub fn finish(self, on_progress: &impl Fn(GenerationProgress)) -> eyre::Result<()> {
+        let mut written_paths = vec![];
+        let mut template_files = vec![];
+        for (planned_entry, entry_result) in self.planned_entries.iter().zip(&self.entry_results) {
+            let Some((is_written, template_file_contents)) = entry_result else {
+                eyre::bail!(
+                    "{} was not generated yet",
+                    planned_entry.output_path.display()
+                );
+            };
+            if *is_written {
+                written_paths.push(&*planned_entry.output_path);
+            }
+            if let PlannedEntryKind::File {
+                template_file_path, ..
+            } = &planned_entry.kind
+            {
+                template_files.push((
+                    &**template_file_path,
+                    template_file_contents.clone().unwrap_or_default(),
+                ));
+            }
+        }
+
+        on_progress(GenerationProgress::Finishing);
+        let provenance = build_provenance(
+            &self.template_id,
+            self.pinned_source.clone(),
+            &self.template_structure,
+            &self.project_name,
+            &self.variable_values,
+            &template_files,
+        )?;
+        std::fs::write(
+            self.staged_root_dir.join(PROVENANCE_FILE_NAME),
+            provenance.to_toml()?,
+        )?;
+        let provenance_path = Path::new(PROVENANCE_FILE_NAME);
+        if !written_paths.contains(&provenance_path) {
+            written_paths.push(provenance_path);
+        }
+
+        let project_root_dir = &self.project_root_dir;
+        if self.is_target_nonempty {
+            let output_paths = written_paths;
+            merge_staged_files(
+                &self.staged_root_dir,
+                project_root_dir,
+                &output_paths,
+                &[],
+                &self.staging_dir.path().join("backup"),
+            )?;
+        } else {
+            if project_root_dir.exists() {
+                std::fs::remove_dir(project_root_dir)?;
+            }
+            std::fs::rename(&self.staged_root_dir, project_root_dir).wrap_err_with(|| {
+                format!(
+                    "could not move the project into {}",
+                    project_root_dir.display()
+                )
+            })?;
+        }
+        if let Some(snapshots_dir) = default_snapshots_dir() {
+            save_snapshot(
+                &snapshots_dir,
+                &provenance,
+                &self.template_structure,
+                &template_files,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Describes a project generated from `template_files`, the unrendered files of the template with id `template_id`
+fn build_provenance(
+    template_id: &str,
+    pinned_source: Option<ArcStr>,
+    template_structure: &TemplateStructure,
+    project_name: &str,
+    variable_values: &VariableValues,
+    template_files: &[(&str, Vec<u8>)],
+) -> eyre::Result<Provenance> {
+    let template_info = get_template_info(template_id)?;
+    Ok(Provenance {
+        scaffy_version: env!("CARGO_PKG_VERSION").into(),
+        registry: template_info.registry,
+        template_path: template_info.path,
+        template_hash: template_hash(
+            &serialize_template_structure(template_structure)?,
+            template_files
+                .iter()
+                .map(|(file_path, file_contents)| (*file_path, &**file_contents)),
+        )
+        .into(),
+        pinned_source,
+        project_name: project_name.into(),
+        variables: variable_values.clone(),
+    })
+}
+
+/// Caches the template files a project was generated from in `snapshots_dir` for `scaffy update`.
+/// The project is usable without them, so failing to save them is only logged.
+pub fn save_snapshot(
+    snapshots_dir: &Path,
+    provenance: &Provenance,
+    template_structure: &TemplateStructure,
+    template_files: &[(&str, Vec<u8>)],
+) {
+    if let Err(err) = Snapshot::save(
+        snapshots_dir,
+        &provenance.template_hash,
+        template_structure,
+        template_files
+            .iter()
+            .map(|(file_path, file_contents)| (*file_path, &**file_contents)),
+    ) {
+        log::warn!(
+            "Could not save a snapshot of {}: {err:#}",
+            provenance.template_path
+        );
+    }
+}
+
+/// Replaces the placeholders in a template file's contents if it's flagged `inject_project_info`.
+/// Binary files are returned as is, even if they happen to contain a placeholder.
+pub fn render_file_contents(
+    template_file_contents: Vec<u8>,
+    inject_project_info: bool,
+    replacer: &ScaffyReplacer,
+) -> Vec<u8> {
+    if inject_project_info && let Ok(file_text) = str::from_utf8(&template_file_contents) {
+        return replacer.replace(file_text).into_bytes();
+    }
+    template_file_contents
+}
+
+#[cfg(unix)]
+pub async fn set_file_mode(file_path: &Path, mode: FileMode) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(file_path, std::fs::Permissions::from_mode(mode.0)).await
+}
+#[cfg(not(unix))]
+pub async fn set_file_mode(_file_path: &Path, _mode: FileMode) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+pub async fn create_symlink(target: &Path, link_path: &Path) -> io::Result<()> {
+    fs::unix::symlink(target, link_path).await
+}
+#[cfg(not(unix))]
+pub async fn create_symlink(_target: &Path, link_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "could not create the symlink {}: symlinks are only supported on Unix",
+            link_path.display()
+        ),
+    ))
+}
+
+/// Creates a hidden directory next to `project_root_dir`, on the same filesystem so its contents
+/// can be renamed into place; it is deleted when dropped.
+pub fn create_staging_dir(project_root_dir: &Path) -> eyre::Result<TempDir> {
+    let parent_dir = match project_root_dir.parent() {
+        Some(parent_dir) if !parent_dir.as_os_str().is_empty() => parent_dir,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(parent_dir)?;
+    tempfile::Builder::new()
+        .prefix(".scaffy-staging-")
+        .tempdir_in(parent_dir)
+        .wrap_err_with(|| {
+            format!(
+                "could not create a staging directory in {}",
+                parent_dir.display()
+            )
+        })
+}
+
+/// Moves the staged files into an existing project directory and deletes `removed_paths` from it,
+/// backing up the files they replace into `backup_dir` so every change can be undone if a later one fails.
+pub fn merge_staged_files(
+    staged_root_dir: &Path,
+    project_root_dir: &Path,
+    output_paths: &[&Path],
+    removed_paths: &[&Path],
+    backup_dir: &Path,
+) -> eyre::Result<()> {
+    let mut moved_files: Vec<(&Path, bool)> = vec![];
+    let changed_paths = output_paths
+        .iter()
+        .map(|output_path| (output_path, true))
+        .chain(
+            removed_paths
+                .iter()
+                .map(|removed_path| (removed_path, false)),
+        );
+    for (output_path, is_staged) in changed_paths {
+        let file_path = project_root_dir.join(output_path);
+        let backup_path = backup_dir.join(output_path);
+        let move_result = (|| -> io::Result<()> {
+            if is_present(&file_path) {
+                if let Some(backup_parent_path) = backup_path.parent() {
+                    std::fs::create_dir_all(backup_parent_path)?;
+                }
+                std::fs::rename(&file_path, &backup_path)?;
+                moved_files.push((output_path, true));
+            } else {
+                moved_files.push((output_path, false));
+            }
+            if !is_staged {
+                return Ok(());
+            }
+            if let Some(file_parent_path) = file_path.parent() {
+                std::fs::create_dir_all(file_parent_path)?;
+            }
+            std::fs::rename(staged_root_dir.join(output_path), &file_path)
+        })();
+        if let Err(err) = move_result {
+            for (moved_path, is_backed_up) in moved_files.into_iter().rev() {
+                let file_path = project_root_dir.join(moved_path);
+                let remove_result = match file_path.symlink_metadata() {
+                    Ok(metadata) if metadata.is_dir() => std::fs::remove_dir(&file_path),
+                    Ok(_) => std::fs::remove_file(&file_path),
+                    Err(_) => Ok(()),
+                };
+                if let Err(err) = remove_result {
+                    log::error!("Could not remove {}: {err}", file_path.display());
+                }
+                if is_backed_up
+                    && let Err(err) = std::fs::rename(backup_dir.join(moved_path), &file_path)
+                {
+                    log::error!("Could not restore {}: {err}", file_path.display());
+                }
+            }
+            return Err(GenerationError {
+                failed_files: vec![FailedFile {
+                    output_path: output_path.to_path_buf(),
+                    error: err.into(),
+                }],
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use super::{
+        ExistingFiles, GenerationCancellation, ProjectGeneration, resolve_output_path,
+        symlink_escapes_root, symlink_passes_through_symlink,
+    };
+    use crate::{
+        registry::{REGISTRIES, Registry},
+        string_ops::ScaffyReplacer,
+        template_info::ArcStr,
+        template_info::{TemplateStructureDirEntryData, load_template_info},
+        template_source::memory::{MemoryTemplateSource, template_info},
+        template_vars::{VariableValue, VariableValues},
+    };
+
+    fn resolve(path: &str, project_name: &str) -> color_eyre::Result<PathBuf> {
+        let mut variable_values = VariableValues::new();
+        variable_values.insert("DIR".into(), VariableValue::String("../../etc".into()));
+        let replacer = ScaffyReplacer::new(project_name, &variable_values);
+        let mut path_parts: Vec<ArcStr> = path.split('/').map(ArcStr::from).collect();
+        let dir_entry_name = path_parts.pop().unwrap();
+        resolve_output_path(&path_parts, &dir_entry_name, &replacer)
+    }
+
+    #[test]
+    fn resolves_plain_paths() {
+        assert_eq!(
+            resolve(
+                "@@SCAFFY_PROJECT_NAME_LOWERKEBABCASE@@/src/main.rs",
+                "My App"
+            )
+            .unwrap(),
+            Path::new("my-app/src/main.rs")
+        );
+        assert_eq!(
+            resolve("..hidden/.config", "x").unwrap(),
+            Path::new("..hidden/.config")
+        );
+    }
+
+    #[test]
+    fn rejects_hostile_entry_names() {
+        for hostile_path in [
+            "../escape.txt",
+            "src/../../escape.txt",
+            "./file",
+            "src//file",
+            "/etc/passwd",
+            "src\\..\\..\\escape.txt",
+            "C:\\Windows\\file",
+            "nul\0byte",
+        ] {
+            let err = resolve(hostile_path, "x").unwrap_err();
+            assert!(
+                err.to_string().contains("template entry"),
+                "{hostile_path}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_hostile_substituted_values() {
+        for hostile_project_name in ["..", "../../home", "/etc", "a/b", "..\\.."] {
+            let err = resolve("@@SCAFFY_PROJECT_NAME@@/file", hostile_project_name).unwrap_err();
+            assert!(
+                err.to_string().contains("would be named"),
+                "{hostile_project_name}: {err}"
+            );
+        }
+        assert!(resolve("@@SCAFFY_VAR_DIR@@/passwd", "x").is_err());
+        assert!(resolve("@@SCAFFY_PROJECT_NAME@@", "").is_err());
+    }
+
+    #[test]
+    fn detects_escaping_symlinks() {
+        assert!(!symlink_escapes_root(
+            Path::new("a/link"),
+            Path::new("../Cargo.toml")
+        ));
+        assert!(!symlink_escapes_root(
+            Path::new("a/b/link"),
+            Path::new("./../../c/../d")
+        ));
+        assert!(symlink_escapes_root(
+            Path::new("link"),
+            Path::new("../outside")
+        ));
+        assert!(symlink_escapes_root(
+            Path::new("a/link"),
+            Path::new("b/../../../outside")
+        ));
+        assert!(symlink_escapes_root(
+            Path::new("a/link"),
+            Path::new("/etc/passwd")
+        ));
+    }
+
+    #[test]
+    fn detects_chained_escaping_symlinks() {
+        let symlink_paths = HashSet::from([Path::new("sub/up"), Path::new("esc")]);
+        // `sub/up -> ..` stays inside on its own, but `esc -> sub/up/..` goes one folder above the root through it
+        assert!(!symlink_escapes_root(Path::new("sub/up"), Path::new("..")));
+        assert!(!symlink_escapes_root(
+            Path::new("esc"),
+            Path::new("sub/up/..")
+        ));
+        assert!(!symlink_passes_through_symlink(
+            Path::new("sub/up"),
+            Path::new(".."),
+            &symlink_paths
+        ));
+        assert!(symlink_passes_through_symlink(
+            Path::new("esc"),
+            Path::new("sub/up/.."),
+            &symlink_paths
+        ));
+        assert!(symlink_passes_through_symlink(
+            Path::new("sub/esc"),
+            Path::new("up/../.."),
+            &symlink_paths
+        ));
+        assert!(symlink_passes_through_symlink(
+            Path::new("a/esc"),
+            Path::new("../sub/x/../up/file"),
+            &symlink_paths
+        ));
+        // Pointing at another symlink is fine, since that one's own target is checked too
+        assert!(!symlink_passes_through_symlink(
+            Path::new("a/link"),
+            Path::new("../sub/up"),
+            &symlink_paths
+        ));
+        assert!(!symlink_passes_through_symlink(
+            Path::new("a/link"),
+            Path::new("../sub/file"),
+            &symlink_paths
+        ));
+    }
+
+    #[test]
+    fn stops_writing_once_cancelled() {
+        let structure = HashMap::from([(
+            "README.md".into(),
+            TemplateStructureDirEntryData::File {
+                inject_project_info: false,
+                condition: None,
+                mode: None,
+            },
+        )]);
+        let source = MemoryTemplateSource::new()
+            .with_template(template_info("docs"), structure)
+            .with_file("docs", "README.md", "# Docs\n");
+        REGISTRIES.with_borrow_mut(|registries| {
+            *registries = vec![Registry::new("scaffy", Arc::new(source)).unwrap()];
+        });
+        smol::block_on(load_template_info()).unwrap();
+
+        let parent_dir = tempfile::tempdir().unwrap();
+        let project_root_dir = parent_dir.path().join("docs");
+        let mut project_generation = smol::block_on(ProjectGeneration::plan(
+            "docs".into(),
+            "docs",
+            &VariableValues::new(),
+            &project_root_dir,
+            &ExistingFiles::Abort,
+            &|_| {},
+        ))
+        .unwrap();
+        let staged_readme_path = project_generation.staged_root_dir.join("README.md");
+        let cancellation = GenerationCancellation::default();
+        cancellation.cancel();
+        let generation_error =
+            smol::block_on(project_generation.write_entries(&|_| {}, &cancellation)).unwrap_err();
+        assert_eq!(generation_error.failed_files.len(), 1);
+        assert!(!staged_readme_path.exists());
+
+        drop(project_generation);
+        assert_eq!(std::fs::read_dir(parent_dir.path()).unwrap().count(), 0);
+    }
+}
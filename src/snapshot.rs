@@ -0,0 +1,115 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use color_eyre::eyre::{self, WrapErr};
+
+use crate::{template_index::serialize_template_structure, template_info::TemplateStructure};
+
+/// How many template revisions are kept in the snapshot cache; the least recently used ones are deleted first
+const MAX_SNAPSHOT_COUNT: usize = 32;
+
+/// A cached copy of the template files a project was generated from, kept in a snapshots directory
+/// (by default `$XDG_DATA_HOME/scaffy/snapshots` on Linux, see [`default_snapshots_dir`]) so
+/// `scaffy update` doesn't have to fetch the revision a project started from again.
+pub struct Snapshot {
+    dir: PathBuf,
+    pub structure: TemplateStructure,
+}
+impl Snapshot {
+    /// Loads the cached revision with `template_hash`, or returns `None` if it isn't cached.
+    pub fn load(snapshots_dir: &Path, template_hash: &str) -> eyre::Result<Option<Self>> {
+        let dir = snapshot_dir(snapshots_dir, template_hash)?;
+        let structure_path = dir.join("structure.json");
+        let Ok(unparsed) = fs::read(&structure_path) else {
+            return Ok(None);
+        };
+        // Marks the snapshot as recently used, so it's the last to be deleted
+        if let Err(err) =
+            fs::File::open(&structure_path).and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            log::warn!("Could not mark {} as used: {err}", dir.display());
+        }
+        Ok(Some(Snapshot {
+            structure: serde_json::from_slice(&unparsed)
+                .wrap_err_with(|| format!("invalid {}", structure_path.display()))?,
+            dir,
+        }))
+    }
+    /// Reads a template file, given its `/`-separated path within the template.
+    pub fn file_contents(&self, template_file_path: &str) -> eyre::Result<Vec<u8>> {
+        let file_path = self.dir.join("files").join(template_file_path);
+        fs::read(&file_path).wrap_err_with(|| format!("could not read {}", file_path.display()))
+    }
+    /// Stores a template revision, unless a snapshot of it already exists, and deletes the least
+    /// recently used snapshots beyond [`MAX_SNAPSHOT_COUNT`].
+    pub fn save<'a>(
+        snapshots_dir: &Path,
+        template_hash: &str,
+        structure: &TemplateStructure,
+        files: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    ) -> eyre::Result<()> {
+        let dir = snapshot_dir(snapshots_dir, template_hash)?;
+        if dir.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(snapshots_dir)?;
+        // Written to a temporary folder first, so an interrupted save never looks like a complete snapshot
+        let staging_dir = tempfile::Builder::new()
+            .prefix(".snapshot-")
+            .tempdir_in(snapshots_dir)?;
+        fs::write(
+            staging_dir.path().join("structure.json"),
+            serialize_template_structure(structure)?,
+        )?;
+        for (template_file_path, file_contents) in files {
+            let file_path = staging_dir.path().join("files").join(template_file_path);
+            if let Some(file_parent_path) = file_path.parent() {
+                fs::create_dir_all(file_parent_path)?;
+            }
+            fs::write(file_path, file_contents)?;
+        }
+        fs::rename(staging_dir.keep(), &dir)?;
+        prune_snapshots(snapshots_dir)
+    }
+}
+
+/// Where snapshots are kept unless another directory is given, or `None` if there is no data directory
+pub fn default_snapshots_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("scaffy").join("snapshots"))
+}
+
+fn snapshot_dir(snapshots_dir: &Path, template_hash: &str) -> eyre::Result<PathBuf> {
+    let Some(hex_hash) = template_hash
+        .strip_prefix("sha256:")
+        .filter(|hex_hash| !hex_hash.is_empty() && hex_hash.chars().all(|c| c.is_ascii_hexdigit()))
+    else {
+        eyre::bail!("invalid template hash \"{template_hash}\"");
+    };
+    Ok(snapshots_dir.join(format!("sha256-{hex_hash}")))
+}
+
+fn prune_snapshots(snapshots_dir: &Path) -> eyre::Result<()> {
+    let mut snapshots = vec![];
+    for dir_entry in fs::read_dir(snapshots_dir)? {
+        let dir_path = dir_entry?.path();
+        if !dir_path
+            .file_name()
+            .is_some_and(|dir_name| dir_name.to_string_lossy().starts_with("sha256-"))
+        {
+            continue;
+        }
+        let last_used = fs::metadata(dir_path.join("structure.json"))
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        snapshots.push((last_used, dir_path));
+    }
+    snapshots.sort_unstable_by(|a, b| b.cmp(a));
+    for (_, dir_path) in snapshots.into_iter().skip(MAX_SNAPSHOT_COUNT) {
+        fs::remove_dir_all(&dir_path)
+            .wrap_err_with(|| format!("could not delete {}", dir_path.display()))?;
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
-use std::{borrow::Cow, cell::LazyCell};
+use std::borrow::Cow;
 
 use aho_corasick::AhoCorasick;
 
+use crate::template_vars::{VariableValues, variable_placeholder};
+
 #[derive(Clone, Copy, Debug)]
 pub enum WordCasing {
     Preserve,
@@ -75,31 +77,40 @@ pub static SCAFFY_CASING_ARGS: &'static [(&'static str, WordCasing, Option<WordC
     ("-", WordCasing::Lower, None),
     ("-", WordCasing::Upper, None),
 ];
-thread_local! {
-    pub static SCAFFY_STRING_SEARCHER: LazyCell<AhoCorasick> = LazyCell::new(|| {
-        let aho_corasick = AhoCorasick::new(SCAFFY_PATTERNS).unwrap();
-        return aho_corasick;
-    })
+/// Replaces every project name placeholder in [`SCAFFY_PATTERNS`] and every
+/// `@@SCAFFY_VAR_<name>@@` placeholder of the given variables.
+pub struct ScaffyReplacer {
+    searcher: AhoCorasick,
+    replacement_strings: Vec<String>,
 }
-
-pub fn scaffy_string_replacement(input: impl AsRef<str>, project_name: impl AsRef<str>) -> String {
-    let input = input.as_ref();
-    let mut output = Vec::with_capacity(input.len());
-    let replacement_strings: Vec<String> = SCAFFY_CASING_ARGS
-        .iter()
-        .map(|&(joiner, word_casing, first_word_casing)| {
-            string_to_casing(
-                project_name.as_ref(),
-                joiner,
-                word_casing,
-                first_word_casing,
-            )
-        })
-        .collect();
-    SCAFFY_STRING_SEARCHER.with(|scaffy_string_searcher| {
-        scaffy_string_searcher
-            .try_stream_replace_all(input.as_bytes(), &mut output, &replacement_strings)
-            .unwrap()
-    });
-    return String::try_from(output).unwrap();
+impl ScaffyReplacer {
+    pub fn new(project_name: impl AsRef<str>, variable_values: &VariableValues) -> Self {
+        let mut patterns = SCAFFY_PATTERNS
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<_>>();
+        let mut replacement_strings = SCAFFY_CASING_ARGS
+            .iter()
+            .map(|&(joiner, word_casing, first_word_casing)| {
+                string_to_casing(
+                    project_name.as_ref(),
+                    joiner,
+                    word_casing,
+                    first_word_casing,
+                )
+            })
+            .collect::<Vec<_>>();
+        for (variable_name, variable_value) in variable_values {
+            patterns.push(variable_placeholder(variable_name));
+            replacement_strings.push(variable_value.to_string());
+        }
+        ScaffyReplacer {
+            searcher: AhoCorasick::new(patterns).unwrap(),
+            replacement_strings,
+        }
+    }
+    pub fn replace(&self, input: impl AsRef<str>) -> String {
+        self.searcher
+            .replace_all(input.as_ref(), &self.replacement_strings)
+    }
 }
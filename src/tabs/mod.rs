@@ -1,12 +1,24 @@
 pub mod project_init;
+pub mod query;
 pub mod search;
 pub mod tag;
+pub mod template_detail;
+pub mod variable_form;
 
 use ratatui::{buffer::Buffer, crossterm::event::Event, layout::Rect};
 
-use crate::app::Commands;
+use crate::{
+    app::{Commands, TaskEvent},
+    error_dialog::ErrorAction,
+};
 
 pub trait Tab {
+    /// Called on the first tab once the event loop starts, so it can spawn tasks
+    fn handle_start(&mut self, _commands: &mut Commands) {}
     fn handle_event(&mut self, _ev: Event, _commands: &mut Commands) {}
+    /// Called with the events sent by tasks this tab spawned through [`Commands::spawn`]
+    fn handle_task_event(&mut self, _task_event: TaskEvent, _commands: &mut Commands) {}
+    /// Called when the user picks [`ErrorAction::Retry`] or [`ErrorAction::Back`] on an error this tab showed
+    fn handle_error_action(&mut self, _error_action: ErrorAction, _commands: &mut Commands) {}
     fn render(&mut self, area: Rect, buf: &mut Buffer);
 }
@@ -1,28 +1,49 @@
+use std::collections::HashSet;
 use std::env;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
 
 use crate::app::Commands;
+use crate::app::TaskEvent;
+use crate::error_dialog::ErrorAction;
+use crate::error_dialog::ErrorDialog;
 use crate::input_widget::visual_input_text;
+use crate::progress_widget::progress_bar;
+use crate::progress_widget::spinner_line;
+use crate::provenance::PROVENANCE_FILE_NAME;
+use crate::scaffold::DryRunEntry;
+use crate::scaffold::ExistingFiles;
+use crate::scaffold::ExistingFilesMode;
+use crate::scaffold::GenerationCancellation;
+use crate::scaffold::GenerationEntry;
+use crate::scaffold::GenerationProgress;
+use crate::scaffold::PlannedAction;
+use crate::scaffold::PlannedEntryKind;
+use crate::scaffold::ProjectGeneration;
+use crate::scaffold::dry_run_project;
+use crate::scaffold::find_existing_files;
+use crate::scaffold::is_nonempty_dir;
+use crate::scaffold::plan_template_structure;
+use crate::string_ops::ScaffyReplacer;
 use crate::string_ops::WordCasing;
-use crate::string_ops::scaffy_string_replacement;
 use crate::string_ops::string_to_casing;
 use crate::tabs::Tab;
+use crate::tabs::variable_form::VariableForm;
 use crate::template_info::ArcStr;
-use crate::template_info::TemplateStructureDirEntryData;
+use crate::template_info::TemplateInfo;
+use crate::template_info::TemplateStructure;
+use crate::template_info::filter_template_structure;
 use crate::template_info::format_template_structure;
-use crate::template_info::get_template_file_contents;
-use crate::template_info::get_template_structure;
-use color_eyre::eyre;
-use futures::future::join_all;
+use crate::template_info::load_template_structure;
+use crate::template_vars::VariableValues;
 use ratatui::crossterm::event::Event;
 use ratatui::crossterm::event::KeyCode;
 use ratatui::crossterm::event::KeyModifiers;
 use ratatui::prelude::*;
 use ratatui::widgets;
 use ratatui::widgets::Block;
-use smol::fs;
+use smol::Task;
+use smol::channel;
 use tui_input::Input;
 use tui_input::backend::crossterm::EventHandler;
 
@@ -36,95 +57,733 @@ pub enum PathPageFocus {
 pub enum ProjectInitPage {
     Preview,
     Name,
-    Path { focus: PathPageFocus },
+    /// Only shown for templates that declare variables
+    Variables,
+    Path {
+        focus: PathPageFocus,
+    },
     Confirmation,
+    /// Shows the progress of the project being generated
+    Generation,
 }
 impl ProjectInitPage {
-    pub fn page_num(&self) -> usize {
+    pub fn page_num(&self, has_variables: bool) -> usize {
+        let variables_page_count = has_variables as usize;
         match self {
             ProjectInitPage::Name => 1,
-            ProjectInitPage::Preview => 2,
-            ProjectInitPage::Path { .. } => 3,
-            ProjectInitPage::Confirmation => 4,
+            ProjectInitPage::Variables => 2,
+            ProjectInitPage::Preview => 2 + variables_page_count,
+            ProjectInitPage::Path { .. } => 3 + variables_page_count,
+            ProjectInitPage::Confirmation => 4 + variables_page_count,
+            ProjectInitPage::Generation => 5 + variables_page_count,
         }
     }
+    pub fn page_count(has_variables: bool) -> usize {
+        ProjectInitPage::Generation.page_num(has_variables)
+    }
 }
 impl ProjectInitPage {
-    pub fn switch_to_next_page(&mut self) {
+    pub fn switch_to_next_page(&mut self, has_variables: bool) {
         *self = match self {
-            ProjectInitPage::Name => ProjectInitPage::Preview,
+            ProjectInitPage::Name if has_variables => ProjectInitPage::Variables,
+            ProjectInitPage::Name | ProjectInitPage::Variables => ProjectInitPage::Preview,
             ProjectInitPage::Preview => ProjectInitPage::Path {
                 focus: PathPageFocus::default(),
             },
             ProjectInitPage::Path { .. } => ProjectInitPage::Confirmation,
-            ProjectInitPage::Confirmation => ProjectInitPage::Confirmation,
+            ProjectInitPage::Confirmation | ProjectInitPage::Generation => {
+                ProjectInitPage::Generation
+            }
         }
     }
 
-    pub fn switch_to_previous_page(&mut self) {
+    pub fn switch_to_previous_page(&mut self, has_variables: bool) {
         *self = match self {
             ProjectInitPage::Name => ProjectInitPage::Name,
+            ProjectInitPage::Variables => ProjectInitPage::Name,
+            ProjectInitPage::Preview if has_variables => ProjectInitPage::Variables,
             ProjectInitPage::Preview => ProjectInitPage::Name,
             ProjectInitPage::Path { .. } => ProjectInitPage::Preview,
             ProjectInitPage::Confirmation => ProjectInitPage::Path {
                 focus: PathPageFocus::default(),
             },
+            ProjectInitPage::Generation => ProjectInitPage::Confirmation,
         }
     }
 }
 
 pub struct ProjectInitTab {
     current_page: ProjectInitPage,
-    template_path: ArcStr,
+    template_id: ArcStr,
     project_name_input: Input,
+    variable_form: VariableForm,
+    variable_values: VariableValues,
     project_parent_path_input: Input,
     project_root_folder_name_input: Input,
     should_autoset_root_folder_name: bool,
+    /// Whether the project path already has files in it, which the Path page warns about; checked when
+    /// the path inputs change, since it reads the folder
+    is_project_path_nonempty: bool,
     preview_scroll_pos: u16,
+    /// Loaded in the background as soon as the tab is opened; `None` while loading
+    template_structure: Option<Result<TemplateStructure, String>>,
+    template_structure_task: Option<Task<()>>,
+    existing_files: ExistingFilesState,
+    dry_run: DryRunState,
+    /// Set once the project starts being generated on the Generation page
+    generation: Option<GenerationState>,
+    /// What the error dialog currently shown is about
+    failed_task: Option<FailedTask>,
+}
+
+/// Background work whose failure is shown in an [`ErrorDialog`], and retried from it
+#[derive(Clone, Copy)]
+enum FailedTask {
+    TemplateStructure,
+    Generation,
+}
+
+/// The Confirmation page's expandable list of the files that would be written
+#[derive(Default)]
+struct DryRunState {
+    is_expanded: bool,
+    /// Loaded the first time the list is expanded
+    entries: Option<Result<Vec<DryRunEntry>, String>>,
+    /// What would be done with each entry under the current existing files mode; worked out when the
+    /// entries load or the mode changes, since it checks the filesystem for every entry
+    actions: Vec<PlannedAction>,
+    task: Option<Task<()>>,
+    scroll_pos: u16,
+}
+
+/// Progress of the project being generated, as reported by [`ProjectGeneration`]
+struct GenerationState {
+    /// Only `None` once dropped
+    task: Option<Task<()>>,
+    cancellation: GenerationCancellation,
+    /// Tells the task to retry the entries that failed
+    retry_sender: channel::Sender<()>,
+    entries: Vec<GenerationEntry>,
+    entry_statuses: Vec<GenerationEntryStatus>,
+    is_finishing: bool,
+    failure: Option<GenerationFailure>,
+    scroll_pos: u16,
+}
+impl Drop for GenerationState {
+    /// Dropping the task would stop it partway through its writes, whose blocking file operations still
+    /// run and could recreate the staging directory after it's deleted. So the task is told to stop
+    /// instead, and left to delete the staging directory once its writes are done.
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+        if let Some(task) = self.task.take() {
+            task.detach();
+        }
+    }
+}
+impl GenerationState {
+    fn count_entries(
+        &self,
+        predicate: impl Fn(&GenerationEntry, &GenerationEntryStatus) -> bool,
+    ) -> usize {
+        self.entries
+            .iter()
+            .zip(&self.entry_statuses)
+            .filter(|(entry, entry_status)| predicate(entry, entry_status))
+            .count()
+    }
+}
+
+enum GenerationEntryStatus {
+    Pending,
+    Downloaded,
+    Written,
+    /// The entry already exists and is kept
+    Skipped,
+    Failed(String),
+}
+
+enum GenerationFailure {
+    /// Some entries could not be generated, and can be retried
+    FailedEntries(usize),
+    /// Generation stopped, and has to be started over
+    Error(String),
+}
+
+/// How the Confirmation page handles a project path that already has files in it
+#[derive(Default)]
+struct ExistingFilesState {
+    is_target_nonempty: bool,
+    mode: ExistingFilesMode,
+    /// Generated files that already exist, relative to the project root
+    existing_paths: Vec<PathBuf>,
+    overwritten_paths: HashSet<PathBuf>,
+    focus: usize,
+}
+impl ExistingFilesState {
+    fn to_existing_files(&self) -> ExistingFiles {
+        match self.mode {
+            ExistingFilesMode::Abort => ExistingFiles::Abort,
+            ExistingFilesMode::Skip => ExistingFiles::Skip,
+            ExistingFilesMode::Overwrite => ExistingFiles::Overwrite,
+            ExistingFilesMode::Ask => ExistingFiles::OverwriteOnly(self.overwritten_paths.clone()),
+        }
+    }
+}
+
+fn existing_files_mode_label(mode: ExistingFilesMode) -> &'static str {
+    match mode {
+        ExistingFilesMode::Abort => "Abort",
+        ExistingFilesMode::Skip => "Skip existing files",
+        ExistingFilesMode::Overwrite => "Overwrite existing files",
+        ExistingFilesMode::Ask => "Choose per file",
+    }
 }
+const EXISTING_FILES_MODES: [ExistingFilesMode; 4] = [
+    ExistingFilesMode::Abort,
+    ExistingFilesMode::Skip,
+    ExistingFilesMode::Overwrite,
+    ExistingFilesMode::Ask,
+];
+
 impl ProjectInitTab {
-    pub fn new(template_path: ArcStr) -> Self {
+    pub fn new(template_info: TemplateInfo, commands: &Commands) -> Self {
         // TODO: Add prev invocation recall
-        ProjectInitTab {
+        let mut project_init_tab = ProjectInitTab {
             current_page: ProjectInitPage::Name,
-            template_path: template_path.clone(),
+            template_id: template_info.id,
+            variable_form: VariableForm::new(&template_info.variables),
+            variable_values: VariableValues::new(),
             project_root_folder_name_input: Input::default(),
             should_autoset_root_folder_name: true,
+            is_project_path_nonempty: false,
             project_parent_path_input: Input::new(
-                env::home_dir().unwrap().to_string_lossy().into(),
+                env::home_dir()
+                    .or_else(|| env::current_dir().ok())
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into(),
             ),
             project_name_input: Input::default(),
             preview_scroll_pos: 0,
-        }
+            template_structure: None,
+            template_structure_task: None,
+            existing_files: ExistingFilesState::default(),
+            dry_run: DryRunState::default(),
+            generation: None,
+            failed_task: None,
+        };
+        project_init_tab.load_template_structure(commands);
+        project_init_tab
+    }
+    /// Fetches the template's structure in the background, which is needed from the Preview page on.
+    fn load_template_structure(&mut self, commands: &Commands) {
+        let template_id = self.template_id.clone();
+        self.template_structure = None;
+        self.template_structure_task = Some(commands.spawn(|task_events| async move {
+            let result = load_template_structure(template_id.clone()).await;
+            task_events.send(TaskEvent::TemplateStructureLoaded {
+                template_id,
+                result,
+            });
+        }));
     }
     pub fn project_path(&self) -> PathBuf {
         let mut path = PathBuf::from(self.project_parent_path_input.value());
         path.push(self.project_root_folder_name_input.value());
         return path;
     }
+    fn update_is_project_path_nonempty(&mut self) {
+        self.is_project_path_nonempty = is_nonempty_dir(&self.project_path());
+    }
+    fn has_variables(&self) -> bool {
+        !self.variable_form.is_empty()
+    }
+    /// Looks for files at the project path that generating the project would write over.
+    fn detect_existing_files(&mut self) {
+        let project_root_dir = self.project_path();
+        self.existing_files = ExistingFilesState {
+            is_target_nonempty: is_nonempty_dir(&project_root_dir),
+            ..ExistingFilesState::default()
+        };
+        if !self.existing_files.is_target_nonempty {
+            return;
+        }
+        let replacer = ScaffyReplacer::new(self.project_name_input.value(), &self.variable_values);
+        if let Some(Ok(template_structure)) = &self.template_structure
+            && let Ok(planned_files) =
+                plan_template_structure(template_structure, &replacer, &self.variable_values)
+        {
+            self.existing_files.existing_paths =
+                find_existing_files(&planned_files, &project_root_dir);
+        }
+    }
+}
+
+impl ProjectInitTab {
+    fn render_existing_files(&self, area: Rect, buf: &mut Buffer) {
+        let existing_files = &self.existing_files;
+        let [mode_area, list_area] =
+            Layout::vertical([Constraint::Length(4), Constraint::Fill(1)]).areas(area);
+        let mode_hint = match existing_files.mode {
+            ExistingFilesMode::Abort => "<ENTER> goes back to the template list",
+            ExistingFilesMode::Skip => "Files that already exist are left untouched",
+            ExistingFilesMode::Overwrite => "Files that already exist are replaced",
+            ExistingFilesMode::Ask => "<UP> / <DOWN> to move, <SPACE> to toggle overwriting a file",
+        };
+        widgets::Paragraph::new(vec![
+            Line::from(vec![
+                Span::raw("Existing files: "),
+                Span::styled(
+                    format!("< {} >", existing_files_mode_label(existing_files.mode)),
+                    Style::new().add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            Line::raw(mode_hint),
+        ])
+        .block(
+            Block::bordered()
+                .title("The project folder already exists and is not empty - <LEFT> / <RIGHT> to change")
+                .border_style(Style::new().fg(Color::Yellow)),
+        )
+        .render(mode_area, buf);
+
+        let visible_path_count = list_area.height.saturating_sub(1) as usize;
+        let first_visible_idx = (existing_files.focus + 1).saturating_sub(visible_path_count);
+        let mut lines = vec![Line::styled(
+            format!(
+                "{} generated file(s) already exist:",
+                existing_files.existing_paths.len()
+            ),
+            Style::new().add_modifier(Modifier::BOLD),
+        )];
+        for (i, existing_path) in existing_files
+            .existing_paths
+            .iter()
+            .enumerate()
+            .skip(first_visible_idx)
+            .take(visible_path_count)
+        {
+            let action = match existing_files.mode {
+                ExistingFilesMode::Abort => "",
+                ExistingFilesMode::Skip => "[skip] ",
+                ExistingFilesMode::Overwrite => "[overwrite] ",
+                ExistingFilesMode::Ask
+                    if existing_files.overwritten_paths.contains(existing_path) =>
+                {
+                    "[x] overwrite "
+                }
+                ExistingFilesMode::Ask => "[ ] skip      ",
+            };
+            let style =
+                if existing_files.mode == ExistingFilesMode::Ask && i == existing_files.focus {
+                    Style::new().fg(Color::Black).bg(Color::LightYellow)
+                } else {
+                    Style::new()
+                };
+            lines.push(Line::styled(
+                format!("{action}{}", existing_path.to_string_lossy()),
+                style,
+            ));
+        }
+        widgets::Paragraph::new(lines).render(list_area, buf);
+    }
+}
+
+impl ProjectInitTab {
+    fn render_dry_run(&mut self, area: Rect, buf: &mut Buffer) {
+        if !self.dry_run.is_expanded {
+            Line::styled(
+                "▸ Files to be written - <TAB> to expand",
+                Style::new().add_modifier(Modifier::BOLD),
+            )
+            .render(area, buf);
+            return;
+        }
+        let [title_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        Line::styled(
+            "▾ Files to be written - <TAB> to collapse | <PAGE UP> / <PAGE DOWN> - Scroll",
+            Style::new().add_modifier(Modifier::BOLD),
+        )
+        .render(title_area, buf);
+        let dry_run_entries = match &self.dry_run.entries {
+            Some(Ok(dry_run_entries)) => dry_run_entries,
+            Some(Err(err)) => {
+                widgets::Paragraph::new(err.as_str())
+                    .style(Style::new().fg(Color::Red))
+                    .render(list_area, buf);
+                return;
+            }
+            None => {
+                spinner_line("Rendering every file...").render(list_area, buf);
+                return;
+            }
+        };
+        let mut lines = vec![Line::styled(
+            format!("{:<10} {:>9}  {:<6}  PATH", "ACTION", "BYTES", "INJECT"),
+            Style::new().fg(Color::Gray),
+        )];
+        for (dry_run_entry, &action) in dry_run_entries.iter().zip(&self.dry_run.actions) {
+            let output_path = dry_run_entry.output_path.to_string_lossy();
+            let path = match &dry_run_entry.kind {
+                PlannedEntryKind::File { .. } => output_path.into_owned(),
+                PlannedEntryKind::EmptyFolder => format!("{output_path}/"),
+                PlannedEntryKind::Symlink { target } => {
+                    format!("{output_path} -> {}", target.to_string_lossy())
+                }
+            };
+            let style = match action {
+                PlannedAction::Create => Style::new(),
+                PlannedAction::Overwrite => Style::new().fg(Color::Yellow),
+                PlannedAction::Skip => Style::new().fg(Color::DarkGray),
+            };
+            lines.push(Line::styled(
+                format!(
+                    "{:<10} {:>9}  {:<6}  {path}",
+                    action.to_string(),
+                    dry_run_entry
+                        .byte_count
+                        .map(|byte_count| byte_count.to_string())
+                        .unwrap_or_else(|| "-".to_owned()),
+                    if dry_run_entry.is_injected {
+                        "yes"
+                    } else {
+                        "no"
+                    },
+                ),
+                style,
+            ));
+        }
+        let max_scroll = (lines.len() as u16).saturating_sub(list_area.height);
+        self.dry_run.scroll_pos = self.dry_run.scroll_pos.min(max_scroll);
+        widgets::Paragraph::new(lines)
+            .scroll((self.dry_run.scroll_pos, 0))
+            .render(list_area, buf);
+    }
+}
+
+impl ProjectInitTab {
+    fn render_generation(&mut self, area: Rect, buf: &mut Buffer) {
+        let project_path = self.project_path();
+        let Some(generation) = &mut self.generation else {
+            return;
+        };
+        let failure_text = match &generation.failure {
+            Some(GenerationFailure::FailedEntries(failed_entry_count)) => Some(format!(
+                "{failed_entry_count} file(s) could not be generated, so no changes were made yet.\nPress <ENTER> to retry them, or <ESC> to abort."
+            )),
+            Some(GenerationFailure::Error(err)) => Some(format!(
+                "{err}\nPress <ENTER> to try again, or <ESC> to go back."
+            )),
+            None => None,
+        };
+        let [
+            title_area,
+            _,
+            downloaded_area,
+            _,
+            written_area,
+            _,
+            list_area,
+        ] = Layout::vertical([
+            Constraint::Length(
+                failure_text
+                    .as_ref()
+                    .map_or(1, |failure_text| failure_text.lines().count() as u16),
+            ),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        if let Some(failure_text) = failure_text {
+            Text::styled(failure_text, Style::new().fg(Color::Red)).render(title_area, buf);
+        } else if generation.is_finishing {
+            spinner_line("Moving the project into place...").render(title_area, buf);
+        } else {
+            let title = format!(
+                "Generating {} at \"{}\"...",
+                self.template_id,
+                project_path.to_string_lossy()
+            );
+            spinner_line(&title).render(title_area, buf);
+        }
+        progress_bar(
+            "Downloaded",
+            generation.count_entries(|entry, entry_status| {
+                entry.is_file
+                    && matches!(
+                        entry_status,
+                        GenerationEntryStatus::Downloaded
+                            | GenerationEntryStatus::Written
+                            | GenerationEntryStatus::Skipped
+                    )
+            }),
+            generation.count_entries(|entry, _| entry.is_file),
+        )
+        .render(downloaded_area, buf);
+        progress_bar(
+            "Written",
+            generation.count_entries(|_, entry_status| {
+                matches!(entry_status, GenerationEntryStatus::Written)
+            }),
+            generation.count_entries(|entry, _| entry.should_write),
+        )
+        .render(written_area, buf);
+
+        // Failed entries are listed first so they can't be missed
+        let mut lines = vec![];
+        let mut failed_lines = vec![];
+        for (entry, entry_status) in generation.entries.iter().zip(&generation.entry_statuses) {
+            let output_path = entry.output_path.to_string_lossy();
+            let (label, style) = match entry_status {
+                GenerationEntryStatus::Pending => {
+                    ("·  pending   ", Style::new().fg(Color::DarkGray))
+                }
+                GenerationEntryStatus::Downloaded => ("↓  downloaded", Style::new()),
+                GenerationEntryStatus::Written => ("✓  written   ", Style::new().fg(Color::Green)),
+                GenerationEntryStatus::Skipped => {
+                    ("-  kept      ", Style::new().fg(Color::DarkGray))
+                }
+                GenerationEntryStatus::Failed(err) => {
+                    failed_lines.push(Line::styled(
+                        format!("✗  failed     {output_path}: {err}"),
+                        Style::new().fg(Color::Red),
+                    ));
+                    continue;
+                }
+            };
+            lines.push(Line::styled(format!("{label} {output_path}"), style));
+        }
+        failed_lines.append(&mut lines);
+        let max_scroll = (failed_lines.len() as u16).saturating_sub(list_area.height);
+        generation.scroll_pos = generation.scroll_pos.min(max_scroll);
+        widgets::Paragraph::new(failed_lines)
+            .scroll((generation.scroll_pos, 0))
+            .render(list_area, buf);
+    }
+}
+
+impl ProjectInitTab {
+    /// Works out what would be done with each entry of the Confirmation page's list of files to be written.
+    fn update_dry_run_actions(&mut self) {
+        let Some(Ok(dry_run_entries)) = &self.dry_run.entries else {
+            self.dry_run.actions.clear();
+            return;
+        };
+        let project_root_dir = self.project_path();
+        let existing_files = self.existing_files.to_existing_files();
+        self.dry_run.actions = dry_run_entries
+            .iter()
+            .map(|dry_run_entry| {
+                if dry_run_entry.output_path == Path::new(PROVENANCE_FILE_NAME) {
+                    dry_run_entry.action
+                } else {
+                    existing_files.planned_action(&project_root_dir, &dry_run_entry.output_path)
+                }
+            })
+            .collect();
+    }
+    /// Renders every file in the background for the Confirmation page's list of files to be written.
+    fn load_dry_run(&mut self, commands: &Commands) {
+        let template_id = self.template_id.clone();
+        let project_name = self.project_name_input.value().to_owned();
+        let variable_values = self.variable_values.clone();
+        let project_root_dir = self.project_path();
+        self.dry_run.task = Some(commands.spawn(|task_events| async move {
+            // Actions are worked out by `update_dry_run_actions`, so they follow the existing files mode
+            let result = dry_run_project(
+                &template_id,
+                &project_name,
+                &variable_values,
+                &project_root_dir,
+                &ExistingFiles::Overwrite,
+            )
+            .await;
+            task_events.send(TaskEvent::DryRunLoaded(result));
+        }));
+    }
+    /// Generates the project in the background, which reports back through [`TaskEvent`]s. If
+    /// some entries fail, the task waits until they are retried through `retry_sender`.
+    fn start_generation(&mut self, commands: &Commands) {
+        let template_id = self.template_id.clone();
+        let project_name = self.project_name_input.value().to_owned();
+        let variable_values = self.variable_values.clone();
+        let project_root_dir = self.project_path();
+        let existing_files = self.existing_files.to_existing_files();
+        let (retry_sender, retry_receiver) = channel::unbounded();
+        let cancellation = GenerationCancellation::default();
+        let task_cancellation = cancellation.clone();
+        let task = commands.spawn(|task_events| async move {
+            let cancellation = task_cancellation;
+            // A cancelled generation keeps quiet, so it can't be confused with one started after it
+            let send = |task_event| {
+                if !cancellation.is_cancelled() {
+                    task_events.send(task_event);
+                }
+            };
+            let on_progress =
+                |generation_progress| send(TaskEvent::GenerationProgressed(generation_progress));
+            let mut project_generation = match ProjectGeneration::plan(
+                template_id,
+                &project_name,
+                &variable_values,
+                &project_root_dir,
+                &existing_files,
+                &on_progress,
+            )
+            .await
+            {
+                Ok(project_generation) => project_generation,
+                Err(err) => {
+                    send(TaskEvent::GenerationFinished(Err(err)));
+                    return;
+                }
+            };
+            if cancellation.is_cancelled() {
+                return;
+            }
+            while let Err(generation_error) = project_generation
+                .write_entries(&on_progress, &cancellation)
+                .await
+            {
+                if cancellation.is_cancelled() {
+                    return;
+                }
+                send(TaskEvent::GenerationEntriesFailed(generation_error));
+                // The sender is only dropped along with the generation's state, which cancels it
+                if retry_receiver.recv().await.is_err() {
+                    return;
+                }
+            }
+            if cancellation.is_cancelled() {
+                return;
+            }
+            send(TaskEvent::GenerationFinished(
+                project_generation.finish(&on_progress),
+            ));
+        });
+        self.generation = Some(GenerationState {
+            task: Some(task),
+            cancellation,
+            retry_sender,
+            entries: vec![],
+            entry_statuses: vec![],
+            is_finishing: false,
+            failure: None,
+            scroll_pos: 0,
+        });
+        self.current_page = ProjectInitPage::Generation;
+    }
+    fn handle_generation_event(&mut self, ev: &Event, commands: &mut Commands) {
+        let Event::Key(key_ev) = ev else {
+            return;
+        };
+        let Some(generation) = &mut self.generation else {
+            return;
+        };
+        match key_ev.code {
+            KeyCode::Esc if !generation.is_finishing => {
+                // Dropping the generation's state cancels it, and its staging directory is deleted once
+                // the writes underway are done
+                self.generation = None;
+                self.current_page = ProjectInitPage::Confirmation;
+                self.update_dry_run_actions();
+            }
+            KeyCode::Enter => match generation.failure {
+                Some(GenerationFailure::FailedEntries(_)) => {
+                    for entry_status in &mut generation.entry_statuses {
+                        if let GenerationEntryStatus::Failed(_) = entry_status {
+                            *entry_status = GenerationEntryStatus::Pending;
+                        }
+                    }
+                    generation.failure = None;
+                    let _ = generation.retry_sender.try_send(());
+                }
+                Some(GenerationFailure::Error(_)) => {
+                    self.start_generation(commands);
+                }
+                None => {}
+            },
+            KeyCode::Down => {
+                generation.scroll_pos = generation.scroll_pos.saturating_add(1);
+            }
+            KeyCode::Up => {
+                generation.scroll_pos = generation.scroll_pos.saturating_sub(1);
+            }
+            KeyCode::PageDown => {
+                generation.scroll_pos = generation.scroll_pos.saturating_add(10);
+            }
+            KeyCode::PageUp => {
+                generation.scroll_pos = generation.scroll_pos.saturating_sub(10);
+            }
+            _ => {}
+        }
+    }
 }
 
 impl Tab for ProjectInitTab {
     fn render(&mut self, area: Rect, buf: &mut Buffer) {
         let border = Block::bordered()
-            .title_bottom(
-                " <ESC> - Exit | <ALT + Q> / <SHIFT + TAB> - Prev Page | <ENTER> - Next Page | <UP> / <DOWN> - Move ",
-            )
-            .title_top(format!(" {} / 4 ", self.current_page.page_num()));
+            .title_bottom(match self.current_page {
+                ProjectInitPage::Generation => {
+                    " <ESC> - Abort | <ENTER> - Retry | <UP> / <DOWN> - Scroll "
+                }
+                _ => {
+                    " <ESC> - Exit | <ALT + Q> / <SHIFT + TAB> - Prev Page | <ENTER> - Next Page | <UP> / <DOWN> - Move "
+                }
+            })
+            .title_top(format!(
+                " {} / {} ",
+                self.current_page.page_num(self.has_variables()),
+                ProjectInitPage::page_count(self.has_variables())
+            ));
         match self.current_page {
             ProjectInitPage::Preview => {
                 let [title_area, preview_area] =
                     Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
                         .areas(border.inner(area));
-                let template_structure =
-                    get_template_structure(self.template_path.clone()).unwrap();
+                let template_structure = match &self.template_structure {
+                    Some(Ok(template_structure)) => template_structure.clone(),
+                    Some(Err(err)) => {
+                        widgets::Paragraph::new(format!(
+                            "Could not load the template's structure: {err}"
+                        ))
+                        .style(Style::new().fg(Color::Red))
+                        .render(border.inner(area), buf);
+                        border.render(area, buf);
+                        return;
+                    }
+                    None => {
+                        spinner_line("Loading the template's structure...")
+                            .render(border.inner(area), buf);
+                        border.render(area, buf);
+                        return;
+                    }
+                };
                 let title = Text::styled(
                     "Template Preview - Press <ENTER> to confirm template selection",
                     Style::new().add_modifier(Modifier::BOLD),
                 );
+                let template_structure =
+                    match filter_template_structure(&template_structure, &self.variable_values) {
+                        Ok(template_structure) => template_structure,
+                        Err(err) => {
+                            widgets::Paragraph::new(format!(
+                                "Could not apply the template's conditions: {err:#}"
+                            ))
+                            .style(Style::new().fg(Color::Red))
+                            .render(border.inner(area), buf);
+                            border.render(area, buf);
+                            return;
+                        }
+                    };
                 let mut preview = widgets::Paragraph::new(format_template_structure(
                     &template_structure,
-                    self.project_name_input.value(),
+                    &ScaffyReplacer::new(self.project_name_input.value(), &self.variable_values),
                 ));
                 let max_scroll =
                     (preview.line_count(preview_area.width) as u16).saturating_sub(preview_area.height);
@@ -135,6 +794,9 @@ impl Tab for ProjectInitTab {
                 title.render(title_area, buf);
                 preview.render(preview_area, buf);
             }
+            ProjectInitPage::Variables => {
+                self.variable_form.render(border.inner(area), buf);
+            }
             ProjectInitPage::Name => {
                 let [input_area] =
                     Layout::vertical([Constraint::Length(3)]).areas(border.inner(area));
@@ -151,13 +813,18 @@ impl Tab for ProjectInitTab {
                 searchbar.render(input_area, buf);
             }
             ProjectInitPage::Path { focus } => {
-                let [parent_path_input_area, root_folder_input_area, stmt_area] =
-                    Layout::vertical([
-                        Constraint::Length(3),
-                        Constraint::Length(3),
-                        Constraint::Length(1),
-                    ])
-                    .areas(border.inner(area));
+                let [
+                    parent_path_input_area,
+                    root_folder_input_area,
+                    stmt_area,
+                    warning_area,
+                ] = Layout::vertical([
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                ])
+                .areas(border.inner(area));
                 if self.should_autoset_root_folder_name {
                     self.project_root_folder_name_input = Input::new(string_to_casing(
                         self.project_name_input.value(),
@@ -166,6 +833,7 @@ impl Tab for ProjectInitTab {
                         None,
                     ));
                     self.should_autoset_root_folder_name = false;
+                    self.update_is_project_path_nonempty();
                 }
                 let (parent_path_input_val, root_folder_input_val) = match focus {
                     PathPageFocus::ParentPathInput => (
@@ -204,16 +872,35 @@ impl Tab for ProjectInitTab {
                 root_folder_input_widget.render(root_folder_input_area, buf);
                 parent_path_input_widget.render(parent_path_input_area, buf);
                 stmt.render(stmt_area, buf);
+                if self.is_project_path_nonempty {
+                    Text::styled(
+                        "This folder already exists and is not empty; you can choose how to handle existing files next.",
+                        Style::new().fg(Color::Yellow),
+                    )
+                    .render(warning_area, buf);
+                }
+            }
+            ProjectInitPage::Generation => {
+                self.render_generation(border.inner(area), buf);
             }
             ProjectInitPage::Confirmation => {
-                let [paragraph_area] =
-                    Layout::vertical([Constraint::Fill(1)]).areas(border.inner(area));
+                let existing_files_height = if self.existing_files.is_target_nonempty {
+                    5 + self.existing_files.existing_paths.len().min(6)
+                } else {
+                    0
+                };
+                let [paragraph_area, existing_files_area, dry_run_area] = Layout::vertical([
+                    Constraint::Length(4),
+                    Constraint::Length(existing_files_height as u16),
+                    Constraint::Fill(1),
+                ])
+                .areas(border.inner(area));
                 let project_path = self.project_path();
                 let paragraph = widgets::Paragraph::new(vec![
                     Line::from(vec![
                         Span::raw("Confirm creation of "),
                         Span::styled(
-                            &*self.template_path,
+                            &*self.template_id,
                             Style::new().add_modifier(Modifier::BOLD),
                         ),
                         Span::raw(" at \""),
@@ -227,6 +914,10 @@ impl Tab for ProjectInitTab {
                     Line::raw("Press <ESC> to exit."),
                 ]);
                 paragraph.render(paragraph_area, buf);
+                if self.existing_files.is_target_nonempty {
+                    self.render_existing_files(existing_files_area, buf);
+                }
+                self.render_dry_run(dry_run_area, buf);
             }
         }
 
@@ -234,47 +925,66 @@ impl Tab for ProjectInitTab {
     }
 
     fn handle_event(&mut self, ev: Event, commands: &mut Commands) {
+        if let ProjectInitPage::Generation = self.current_page {
+            self.handle_generation_event(&ev, commands);
+            return;
+        }
         match &ev {
             Event::Key(key_ev) => match key_ev.code {
                 KeyCode::Char('q') if key_ev.modifiers.contains(KeyModifiers::ALT) => {
-                    self.current_page.switch_to_previous_page();
+                    self.current_page
+                        .switch_to_previous_page(self.has_variables());
                     return;
                 }
-                KeyCode::Tab if key_ev.modifiers.contains(KeyModifiers::SHIFT) => {
-                    self.current_page.switch_to_previous_page();
+                KeyCode::Tab | KeyCode::BackTab
+                    if key_ev.modifiers.contains(KeyModifiers::SHIFT) =>
+                {
+                    self.current_page
+                        .switch_to_previous_page(self.has_variables());
                     return;
                 }
 
                 KeyCode::Enter => match &self.current_page {
-                    ProjectInitPage::Preview | ProjectInitPage::Path { .. } => {
-                        self.current_page.switch_to_next_page();
+                    ProjectInitPage::Preview => {
+                        // The preview shows why the template can't be generated if its conditions are broken
+                        if let Some(Ok(template_structure)) = &self.template_structure
+                            && filter_template_structure(template_structure, &self.variable_values)
+                                .is_ok()
+                        {
+                            self.current_page.switch_to_next_page(self.has_variables());
+                        }
+                        return;
+                    }
+                    ProjectInitPage::Path { .. } => {
+                        self.detect_existing_files();
+                        self.dry_run = DryRunState::default();
+                        self.current_page.switch_to_next_page(self.has_variables());
                         return;
                     }
                     ProjectInitPage::Name => {
                         if self.project_name_input.value().len() != 0 {
-                            self.current_page.switch_to_next_page();
+                            self.current_page.switch_to_next_page(self.has_variables());
+                        }
+                        return;
+                    }
+                    ProjectInitPage::Variables => {
+                        if let Some(variable_values) = self.variable_form.validate() {
+                            self.variable_values = variable_values;
+                            self.current_page.switch_to_next_page(self.has_variables());
                         }
                         return;
                     }
                     ProjectInitPage::Confirmation => {
-                        let project_root_dir = self.project_path();
-                        init_project(
-                            self.template_path.clone(),
-                            self.project_name_input.value(),
-                            &project_root_dir,
-                        )
-                        .unwrap();
-
-                        commands.quit(Some(Box::new(move || {
-                            println!(
-                                "Project at {} created! To enter the project, run",
-                                project_root_dir.to_string_lossy()
-                            );
-                            println!("cd {}", project_root_dir.to_string_lossy());
-                            println!("Good luck!");
-                        })));
-                        env::set_current_dir(self.project_path()).unwrap();
+                        if self.existing_files.is_target_nonempty
+                            && self.existing_files.mode == ExistingFilesMode::Abort
+                        {
+                            commands.switch_tab_to_cached();
+                            return;
+                        }
+                        self.start_generation(commands);
+                        return;
                     }
+                    ProjectInitPage::Generation => {}
                 },
                 KeyCode::Esc => {
                     commands.switch_tab_to_cached();
@@ -287,7 +997,80 @@ impl Tab for ProjectInitTab {
             ProjectInitPage::Name => {
                 self.project_name_input.handle_event(&ev);
             }
-            ProjectInitPage::Confirmation => {}
+            ProjectInitPage::Variables => {
+                self.variable_form.handle_event(&ev);
+            }
+            // Handled by `handle_generation_event`
+            ProjectInitPage::Generation => {}
+            ProjectInitPage::Confirmation => {
+                if let Event::Key(key_ev) = &ev {
+                    match key_ev.code {
+                        KeyCode::Tab => {
+                            self.dry_run.is_expanded = !self.dry_run.is_expanded;
+                            if self.dry_run.is_expanded
+                                && self.dry_run.entries.is_none()
+                                && self.dry_run.task.is_none()
+                            {
+                                self.load_dry_run(commands);
+                            }
+                        }
+                        KeyCode::PageDown => {
+                            self.dry_run.scroll_pos = self.dry_run.scroll_pos.saturating_add(5);
+                        }
+                        KeyCode::PageUp => {
+                            self.dry_run.scroll_pos = self.dry_run.scroll_pos.saturating_sub(5);
+                        }
+                        _ => {}
+                    }
+                }
+                if self.existing_files.is_target_nonempty
+                    && let Event::Key(key_ev) = &ev
+                {
+                    let existing_files = &mut self.existing_files;
+                    let mode_idx = EXISTING_FILES_MODES
+                        .iter()
+                        .position(|mode| *mode == existing_files.mode)
+                        .unwrap_or(0);
+                    let existing_path_count = existing_files.existing_paths.len();
+                    match key_ev.code {
+                        KeyCode::Left => {
+                            existing_files.mode = EXISTING_FILES_MODES[mode_idx
+                                .checked_sub(1)
+                                .unwrap_or(EXISTING_FILES_MODES.len() - 1)];
+                        }
+                        KeyCode::Right => {
+                            existing_files.mode =
+                                EXISTING_FILES_MODES[(mode_idx + 1) % EXISTING_FILES_MODES.len()];
+                        }
+                        KeyCode::Up if existing_path_count > 0 => {
+                            existing_files.focus = existing_files
+                                .focus
+                                .checked_sub(1)
+                                .unwrap_or(existing_path_count - 1);
+                        }
+                        KeyCode::Down if existing_path_count > 0 => {
+                            existing_files.focus = (existing_files.focus + 1) % existing_path_count;
+                        }
+                        KeyCode::Char(' ') if existing_files.mode == ExistingFilesMode::Ask => {
+                            if let Some(existing_path) =
+                                existing_files.existing_paths.get(existing_files.focus)
+                                && !existing_files.overwritten_paths.remove(existing_path)
+                            {
+                                existing_files
+                                    .overwritten_paths
+                                    .insert(existing_path.clone());
+                            }
+                        }
+                        _ => {}
+                    }
+                    if matches!(
+                        key_ev.code,
+                        KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                    ) {
+                        self.update_dry_run_actions();
+                    }
+                }
+            }
             ProjectInitPage::Preview => match &ev {
                 Event::Key(key_ev) => match key_ev.code {
                     KeyCode::Down => {
@@ -309,80 +1092,132 @@ impl Tab for ProjectInitTab {
                         PathPageFocus::RootFolderInput => PathPageFocus::ParentPathInput,
                     };
                 }
-                ev => match focus {
-                    PathPageFocus::ParentPathInput => {
-                        self.project_parent_path_input.handle_event(&ev);
-                    }
-                    PathPageFocus::RootFolderInput => {
-                        self.project_root_folder_name_input.handle_event(&ev);
+                ev => {
+                    let state_changed = match focus {
+                        PathPageFocus::ParentPathInput => {
+                            self.project_parent_path_input.handle_event(&ev)
+                        }
+                        PathPageFocus::RootFolderInput => {
+                            self.project_root_folder_name_input.handle_event(&ev)
+                        }
+                    };
+                    if state_changed.is_some_and(|state_changed| state_changed.value) {
+                        self.update_is_project_path_nonempty();
                     }
-                },
+                }
             },
         }
     }
-}
-
-fn init_project(
-    template_path: ArcStr,
-    project_name: &str,
-    project_root_dir: &Path,
-) -> eyre::Result<()> {
-    let template_structure = get_template_structure(template_path.clone())?;
-    let mut stack: Vec<(ArcStr, TemplateStructureDirEntryData, Vec<ArcStr>)> = template_structure
-        .into_iter()
-        .map(|(dir_entry_name, dir_entry)| (dir_entry_name, dir_entry, vec![]))
-        .collect();
 
-    let mut tasks = vec![];
-    while let Some((dir_entry_name, dir_entry, parent_path)) = stack.pop() {
-        let joined_parent_path: ArcStr = Arc::from(parent_path.join("/"));
-        match dir_entry {
-            TemplateStructureDirEntryData::Folder { children, .. } => {
-                stack.extend(
-                    children
-                        .into_iter()
-                        .map(|(sub_dir_entry_name, sub_dir_entry)| {
-                            let mut sub_dir_parent_path = parent_path.clone();
-                            sub_dir_parent_path.push(dir_entry_name.clone());
-                            (sub_dir_entry_name, sub_dir_entry, sub_dir_parent_path)
-                        }),
-                );
+    fn handle_task_event(&mut self, task_event: TaskEvent, commands: &mut Commands) {
+        match task_event {
+            TaskEvent::TemplateStructureLoaded {
+                template_id,
+                result,
+            } if template_id == self.template_id => {
+                self.template_structure_task = None;
+                if let Err(err) = &result {
+                    self.failed_task = Some(FailedTask::TemplateStructure);
+                    commands.show_error(
+                        ErrorDialog::new("Could not load the template's structure", err)
+                            .with_retry(),
+                    );
+                }
+                self.template_structure = Some(result.map_err(|err| format!("{err:#}")));
             }
-            TemplateStructureDirEntryData::File {
-                inject_project_info,
-            } => {
-                let joined_parent_path = joined_parent_path.clone();
-                let dir_entry_name = dir_entry_name.clone();
-                let template_path = template_path.clone();
-
-                tasks.push(Box::pin(async move {
-                    let mut file_contents = get_template_file_contents(
-                        template_path,
-                        joined_parent_path,
-                        dir_entry_name.clone(),
-                    )
-                    .await?;
-                    let mut file_parent_path = project_root_dir.to_owned();
-                    for path_part in parent_path {
-                        let formatted_path_part =
-                            scaffy_string_replacement(path_part, project_name);
-                        file_parent_path.push(&*formatted_path_part);
+            TaskEvent::DryRunLoaded(result) if self.dry_run.task.take().is_some() => {
+                self.dry_run.entries = Some(result.map_err(|err| format!("{err:#}")));
+                self.update_dry_run_actions();
+            }
+            TaskEvent::GenerationProgressed(generation_progress) => {
+                let Some(generation) = &mut self.generation else {
+                    return;
+                };
+                let (entry_idx, entry_status) = match generation_progress {
+                    GenerationProgress::Planned { entries } => {
+                        generation.entry_statuses = entries
+                            .iter()
+                            .map(|_| GenerationEntryStatus::Pending)
+                            .collect();
+                        generation.entries = entries;
+                        return;
+                    }
+                    GenerationProgress::Downloaded { entry_idx } => {
+                        (entry_idx, GenerationEntryStatus::Downloaded)
                     }
-                    fs::create_dir_all(&file_parent_path).await?;
-                    let formatted_dir_entry_name =
-                        scaffy_string_replacement(dir_entry_name, project_name);
-                    let file_path = file_parent_path.join(formatted_dir_entry_name);
-                    if inject_project_info {
-                        file_contents = scaffy_string_replacement(file_contents, project_name);
+                    GenerationProgress::Written { entry_idx } => {
+                        (entry_idx, GenerationEntryStatus::Written)
                     }
-                    fs::write(file_path, file_contents).await?;
-                    Ok::<(), eyre::Error>(())
-                }));
+                    GenerationProgress::Skipped { entry_idx } => {
+                        (entry_idx, GenerationEntryStatus::Skipped)
+                    }
+                    GenerationProgress::Failed { entry_idx, error } => {
+                        (entry_idx, GenerationEntryStatus::Failed(error))
+                    }
+                    GenerationProgress::Finishing => {
+                        generation.is_finishing = true;
+                        return;
+                    }
+                };
+                if let Some(status) = generation.entry_statuses.get_mut(entry_idx) {
+                    *status = entry_status;
+                }
+            }
+            TaskEvent::GenerationEntriesFailed(generation_error) => {
+                if let Some(generation) = &mut self.generation {
+                    generation.failure = Some(GenerationFailure::FailedEntries(
+                        generation_error.failed_files.len(),
+                    ));
+                }
+            }
+            TaskEvent::GenerationFinished(result) => {
+                let Some(generation) = &mut self.generation else {
+                    return;
+                };
+                if let Err(err) = result {
+                    generation.is_finishing = false;
+                    generation.failure = Some(GenerationFailure::Error(format!("{err:#}")));
+                    self.failed_task = Some(FailedTask::Generation);
+                    commands.show_error(
+                        ErrorDialog::new("Could not generate the project", &err).with_retry(),
+                    );
+                    return;
+                }
+                let project_root_dir = self.project_path();
+                commands.quit(Some(Box::new(move || {
+                    println!(
+                        "Project at {} created! To enter the project, run",
+                        project_root_dir.to_string_lossy()
+                    );
+                    println!("cd {}", project_root_dir.to_string_lossy());
+                    println!("Good luck!");
+                })));
+                if let Err(err) = env::set_current_dir(self.project_path()) {
+                    log::warn!("Could not enter the generated project: {err}");
+                }
+            }
+            _ => {}
+        }
+    }
+    fn handle_error_action(&mut self, error_action: ErrorAction, commands: &mut Commands) {
+        let Some(failed_task) = self.failed_task.take() else {
+            return;
+        };
+        match (failed_task, error_action) {
+            (FailedTask::TemplateStructure, ErrorAction::Retry) => {
+                self.load_template_structure(commands);
+            }
+            (FailedTask::TemplateStructure, _) => {
+                commands.switch_tab_to_cached();
+            }
+            (FailedTask::Generation, ErrorAction::Retry) => {
+                self.start_generation(commands);
+            }
+            (FailedTask::Generation, _) => {
+                self.generation = None;
+                self.current_page = ProjectInitPage::Confirmation;
+                self.update_dry_run_actions();
             }
         }
     }
-    smol::block_on(join_all(&mut tasks))
-        .into_iter()
-        .collect::<eyre::Result<Vec<()>>>()?;
-    Ok(())
 }
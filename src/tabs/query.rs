@@ -0,0 +1,216 @@
+use crate::tabs::tag::TagType;
+
+/// A parsed searchbar query, whose terms must all hold for a template to match
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    pub terms: Vec<QueryTerm>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QueryTerm {
+    /// Set by a leading `-`, which makes templates the term holds for not match
+    pub is_negated: bool,
+    pub kind: QueryTermKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryTermKind {
+    /// A plain word, fuzzily matched against every field
+    Word(String),
+    /// A `"quoted phrase"`, which must appear as is in some field
+    Phrase(String),
+    /// `lang:`, `framework:`, `lib:` or `misc:` for a tag of that type, or `tag:` for a tag of any type,
+    /// optionally followed by `@<version>`
+    Tag {
+        tag_type: Option<TagType>,
+        name: String,
+        version: Option<String>,
+    },
+    /// `author:`
+    Author(String),
+}
+
+impl SearchQuery {
+    /// Parses space-separated terms, where quotes keep spaces within a term, e.g.
+    /// `lang:rust -tag:wasm "web app" author:"Jane Doe"`.
+    /// Qualifiers with nothing after the colon are ignored, so the results don't change while one is being typed.
+    pub fn parse(query: &str) -> Self {
+        SearchQuery {
+            terms: split_terms(query)
+                .into_iter()
+                .filter_map(|term| parse_term(&term))
+                .collect(),
+        }
+    }
+}
+
+/// Splits `query` on spaces outside of quotes, keeping the quotes
+fn split_terms(query: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut is_in_quotes = false;
+    for c in query.chars() {
+        if c == '"' {
+            is_in_quotes = !is_in_quotes;
+        }
+        if c.is_whitespace() && !is_in_quotes {
+            if !term.is_empty() {
+                terms.push(std::mem::take(&mut term));
+            }
+        } else {
+            term.push(c);
+        }
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    terms
+}
+
+fn parse_term(term: &str) -> Option<QueryTerm> {
+    let (is_negated, term) = match term.strip_prefix('-') {
+        Some(negated_term) if !negated_term.is_empty() => (true, negated_term),
+        _ => (false, term),
+    };
+    let kind = if term.starts_with('"') {
+        QueryTermKind::Phrase(unquote(term).to_owned())
+    } else if let Some((qualifier, value)) = term.split_once(':')
+        && let Some(kind) = parse_qualified_term(qualifier, unquote(value))
+    {
+        kind?
+    } else {
+        QueryTermKind::Word(term.to_owned())
+    };
+    Some(QueryTerm { is_negated, kind })
+}
+
+/// Returns `None` if `qualifier` isn't known, and `Some(None)` if it is but `value` is empty.
+fn parse_qualified_term(qualifier: &str, value: &str) -> Option<Option<QueryTermKind>> {
+    let tag_type = match &*qualifier.to_lowercase() {
+        "lang" | "language" => Some(TagType::Language),
+        "framework" => Some(TagType::Framework),
+        "lib" | "library" => Some(TagType::Library),
+        "misc" => Some(TagType::Misc),
+        "tag" => None,
+        "author" => {
+            return Some((!value.is_empty()).then(|| QueryTermKind::Author(value.to_owned())));
+        }
+        _ => return None,
+    };
+    let (name, version) = match value.split_once('@') {
+        Some((name, version)) if !version.is_empty() => (name, Some(version.to_owned())),
+        Some((name, _)) => (name, None),
+        None => (value, None),
+    };
+    Some((!name.is_empty()).then(|| QueryTermKind::Tag {
+        tag_type,
+        name: name.to_owned(),
+        version,
+    }))
+}
+
+fn unquote(text: &str) -> &str {
+    let text = text.strip_prefix('"').unwrap_or(text);
+    text.strip_suffix('"').unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryTerm, QueryTermKind, SearchQuery};
+    use crate::tabs::tag::TagType;
+
+    fn parse(query: &str) -> Vec<QueryTerm> {
+        SearchQuery::parse(query).terms
+    }
+    fn term(kind: QueryTermKind) -> QueryTerm {
+        QueryTerm {
+            is_negated: false,
+            kind,
+        }
+    }
+    fn tag(tag_type: Option<TagType>, name: &str, version: Option<&str>) -> QueryTermKind {
+        QueryTermKind::Tag {
+            tag_type,
+            name: name.to_owned(),
+            version: version.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn parses_qualified_tags() {
+        assert_eq!(
+            parse("lang:rust LIBRARY:serde misc:cli"),
+            [
+                term(tag(Some(TagType::Language), "rust", None)),
+                term(tag(Some(TagType::Library), "serde", None)),
+                term(tag(Some(TagType::Misc), "cli", None)),
+            ]
+        );
+        assert_eq!(
+            parse("framework:react@19 tag:wasm-pack@"),
+            [
+                term(tag(Some(TagType::Framework), "react", Some("19"))),
+                term(tag(None, "wasm-pack", None)),
+            ]
+        );
+        assert_eq!(
+            parse("author:\"Jane Doe\""),
+            [term(QueryTermKind::Author("Jane Doe".to_owned()))]
+        );
+    }
+
+    #[test]
+    fn parses_negated_terms() {
+        assert_eq!(
+            parse("lang:rust -tag:wasm -cli"),
+            [
+                term(tag(Some(TagType::Language), "rust", None)),
+                QueryTerm {
+                    is_negated: true,
+                    kind: tag(None, "wasm", None),
+                },
+                QueryTerm {
+                    is_negated: true,
+                    kind: QueryTermKind::Word("cli".to_owned()),
+                },
+            ]
+        );
+        // A lone `-` is a word rather than an empty negated term
+        assert_eq!(parse("-"), [term(QueryTermKind::Word("-".to_owned()))]);
+    }
+
+    #[test]
+    fn keeps_spaces_in_quoted_phrases() {
+        assert_eq!(
+            parse("  \"web  app\"   starter "),
+            [
+                term(QueryTermKind::Phrase("web  app".to_owned())),
+                term(QueryTermKind::Word("starter".to_owned())),
+            ]
+        );
+        // An unterminated quote runs to the end of the query
+        assert_eq!(
+            parse("rust \"web app"),
+            [
+                term(QueryTermKind::Word("rust".to_owned())),
+                term(QueryTermKind::Phrase("web app".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_empty_qualifiers_and_keeps_unknown_ones_as_words() {
+        assert_eq!(parse("lang: tag:@19 author:"), []);
+        assert_eq!(
+            parse("lang: rust"),
+            [term(QueryTermKind::Word("rust".to_owned()))]
+        );
+        assert_eq!(
+            parse("os:linux http://x"),
+            [
+                term(QueryTermKind::Word("os:linux".to_owned())),
+                term(QueryTermKind::Word("http://x".to_owned())),
+            ]
+        );
+    }
+}
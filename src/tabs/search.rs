@@ -1,21 +1,41 @@
-use std::iter;
+use std::{collections::BTreeSet, iter};
 
 use crate::{
-    app::Commands,
+    app::{Commands, TaskEvent},
+    error_dialog::{ErrorAction, ErrorDialog},
     input_widget::visual_input_text,
+    progress_widget::spinner_line,
+    registry::stale_registry_names,
     tabs::{
         Tab,
         project_init::ProjectInitTab,
-        tag::{Tag, parse_template_info_tags},
+        query::{QueryTerm, QueryTermKind, SearchQuery},
+        tag::{Tag, TagType, parse_template_info_tags},
+        template_detail::TemplateDetailTab,
     },
-    template_info::{ArcStr, TEMPLATE_INFOS},
+    template_info::{ArcStr, TEMPLATE_INFOS, TemplateInfo, get_template_info, load_template_info},
 };
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use ratatui::{
-    crossterm::event::{Event, KeyCode},
+    crossterm::event::{Event, KeyCode, KeyModifiers},
     prelude::*,
     widgets::{self, Block, ListState},
 };
+use smol::Task;
 use tui_input::{Input, backend::crossterm::EventHandler};
+
+/// Fuzzy matches scoring less than this per query character are too scattered to count,
+/// which would otherwise let short queries match most long descriptions
+const MIN_MATCH_SCORE_PER_CHAR: i64 = 12;
+/// What each character of a quoted phrase scores, about as much as in a contiguous fuzzy match
+const PHRASE_MATCH_SCORE_PER_CHAR: i64 = 20;
+/// What each character of a query word scores when it only matches a word with a typo, less than any fuzzy match
+const TYPO_MATCH_SCORE_PER_CHAR: i64 = 8;
+/// Query words shorter than this aren't matched with a typo, since they'd be one edit away from most short words
+const MIN_TYPO_MATCH_LEN: usize = 3;
+/// Width of the tag sidebar, including its borders
+const SIDEBAR_WIDTH: u16 = 30;
+
 #[derive(Default)]
 pub struct ScaffoldTab {
     focus: ScaffoldTabFocus,
@@ -24,34 +44,139 @@ pub struct ScaffoldTab {
     areas: ScaffoldTabAreas,
     list_data_search_query: String,
     list_data: Vec<ScaffoldListEntry>,
+    stale_registries: Vec<ArcStr>,
+    /// Set while the template catalog is loading
+    catalog_task: Option<Task<()>>,
+    is_sidebar_shown: bool,
+    sidebar_state: ListState,
+    /// Every tag of the catalog, grouped by type
+    tag_facets: Vec<TagFacet>,
+    /// Tags picked in the sidebar, which listed templates must all have
+    selected_tags: BTreeSet<(TagType, ArcStr)>,
+}
+
+/// A tag listed in the sidebar
+#[derive(Debug)]
+struct TagFacet {
+    tag_type: TagType,
+    name: ArcStr,
+    /// How many of the listed templates have the tag
+    template_count: usize,
 }
 
 impl ScaffoldTab {
     pub fn new() -> Self {
-        let mut scaffold_tab = ScaffoldTab::default();
+        let mut scaffold_tab = ScaffoldTab {
+            stale_registries: stale_registry_names(),
+            ..ScaffoldTab::default()
+        };
         scaffold_tab.update_list();
         scaffold_tab
     }
     fn update_list(&mut self) {
+        let mut search_query = SearchQuery::parse(&self.list_data_search_query);
+        search_query
+            .terms
+            .extend(self.selected_tags.iter().map(|(tag_type, name)| QueryTerm {
+                is_negated: false,
+                kind: QueryTermKind::Tag {
+                    tag_type: Some(*tag_type),
+                    name: name.to_string(),
+                    version: None,
+                },
+            }));
         self.list_data = TEMPLATE_INFOS.with(|template_infos| {
             template_infos
                 .borrow()
                 .values()
-                .map(|template_info| {
-                    ScaffoldListEntry::new(
-                        template_info.name.clone(),
-                        template_info.path.clone(),
-                        template_info.author.clone(),
-                        template_info.description.clone(),
-                        parse_template_info_tags(&template_info.tags),
-                    )
-                })
-                .filter(|scaffold_list_entry| {
-                    scaffold_list_entry.matches_query(&self.list_data_search_query)
+                .filter_map(|template_info| {
+                    let mut scaffold_list_entry =
+                        ScaffoldListEntry::from_template_info(template_info);
+                    scaffold_list_entry.query_match =
+                        scaffold_list_entry.match_query(&search_query)?;
+                    Some(scaffold_list_entry)
                 })
                 .collect()
         });
+        self.list_data.sort_by(|list_entry_1, list_entry_2| {
+            list_entry_2
+                .query_match
+                .rank()
+                .cmp(&list_entry_1.query_match.rank())
+                .then_with(|| list_entry_1.template_name.cmp(&list_entry_2.template_name))
+                .then_with(|| list_entry_1.template_id.cmp(&list_entry_2.template_id))
+        });
         self.list_state.select(Some(0));
+        self.update_tag_facets();
+    }
+    fn update_tag_facets(&mut self) {
+        let known_tags = TEMPLATE_INFOS.with_borrow(|template_infos| {
+            template_infos
+                .values()
+                .flat_map(|template_info| parse_template_info_tags(&template_info.tags))
+                .map(|tag| (tag.tag_type, tag.text))
+                .collect::<BTreeSet<_>>()
+        });
+        self.tag_facets = known_tags
+            .into_iter()
+            .map(|(tag_type, name)| {
+                let template_count = self
+                    .list_data
+                    .iter()
+                    .filter(|list_entry| {
+                        list_entry
+                            .tags
+                            .iter()
+                            .any(|tag| tag.tag_type == tag_type && tag.text == name)
+                    })
+                    .count();
+                TagFacet {
+                    tag_type,
+                    name,
+                    template_count,
+                }
+            })
+            .collect();
+        if self
+            .sidebar_state
+            .selected()
+            .is_none_or(|selected_idx| selected_idx >= self.tag_facets.len())
+        {
+            self.sidebar_state.select(Some(0));
+        }
+    }
+    fn toggle_selected_tag(&mut self) {
+        let Some(tag_facet) = self
+            .sidebar_state
+            .selected()
+            .and_then(|selected_idx| self.tag_facets.get(selected_idx))
+        else {
+            return;
+        };
+        let tag = (tag_facet.tag_type, tag_facet.name.clone());
+        if !self.selected_tags.remove(&tag) {
+            self.selected_tags.insert(tag);
+        }
+        self.update_list();
+    }
+    fn load_catalog(&mut self, commands: &Commands) {
+        self.catalog_task = Some(commands.spawn(|task_events| async move {
+            task_events.send(TaskEvent::CatalogLoaded(load_template_info().await));
+        }));
+    }
+    fn selected_template_id(&self) -> Option<ArcStr> {
+        let list_entry = self.list_data.get(self.list_state.selected()?)?;
+        Some(list_entry.template_id.clone())
+    }
+    fn selected_template_info(&self, commands: &mut Commands) -> Option<TemplateInfo> {
+        let template_id = self.selected_template_id()?;
+        match get_template_info(&template_id) {
+            Ok(template_info) => Some(template_info),
+            Err(err) => {
+                commands.show_error(ErrorDialog::new("Could not open the template", &err));
+                None
+            }
+        }
     }
 }
 
@@ -59,14 +184,61 @@ impl ScaffoldTab {
 pub struct ScaffoldListEntry {
     template_name: ArcStr,
     template_id: ArcStr,
+    source: ArcStr,
     author: ArcStr,
     desc: ArcStr,
     tags: Vec<Tag>,
+    /// Where the current search query matched, set when the list is updated
+    query_match: QueryMatch,
+}
+
+/// How well a search query matched a [`ScaffoldListEntry`], and which characters of each field it matched
+#[derive(Debug, Default)]
+pub struct QueryMatch {
+    /// Sum of the [`QueryField::rank`] of the field each term matched
+    field_rank: usize,
+    score: i64,
+    name_indices: Vec<usize>,
+    source_indices: Vec<usize>,
+    author_indices: Vec<usize>,
+    desc_indices: Vec<usize>,
+    /// Matched characters of each tag's text, in the same order as the tags
+    tag_indices: Vec<Vec<usize>>,
+}
+
+impl QueryMatch {
+    /// Orders matches from the worst to the best: first by which fields the terms matched, so a name
+    /// match always beats a tag match, which beats any other, then by how closely they matched.
+    pub fn rank(&self) -> (usize, i64) {
+        (self.field_rank, self.score)
+    }
+}
+
+/// A field of a [`ScaffoldListEntry`] that a query can match
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QueryField {
+    Name,
+    Source,
+    Author,
+    Desc,
+    Tag(usize),
 }
+impl QueryField {
+    /// How much a term matching this field counts, before how closely it matched
+    fn rank(&self) -> usize {
+        match self {
+            QueryField::Name => 2,
+            QueryField::Tag(_) => 1,
+            QueryField::Source | QueryField::Author | QueryField::Desc => 0,
+        }
+    }
+}
+
 impl ScaffoldListEntry {
     pub fn new(
         template_name: ArcStr,
         template_id: ArcStr,
+        source: ArcStr,
         author: ArcStr,
         desc: ArcStr,
         tags: Vec<Tag>,
@@ -74,22 +246,226 @@ impl ScaffoldListEntry {
         ScaffoldListEntry {
             template_name,
             template_id,
+            source,
             author,
             desc,
             tags,
+            query_match: QueryMatch::default(),
         }
     }
-    pub fn matches_query<'a>(&self, queries: &str) -> bool {
-        queries.split(" ").all(|query| {
-            self.template_name.to_lowercase().contains(query)
-                || self.desc.to_lowercase().contains(query)
-                || self
-                    .tags
-                    .iter()
-                    .any(|tag| tag.text.to_lowercase().contains(query))
-                || self.author.to_lowercase().contains(query)
-        })
+    pub fn from_template_info(template_info: &TemplateInfo) -> Self {
+        ScaffoldListEntry::new(
+            template_info.name.clone(),
+            template_info.id.clone(),
+            template_info.registry.clone(),
+            template_info.author.clone(),
+            template_info.description.clone(),
+            parse_template_info_tags(&template_info.tags),
+        )
+    }
+    /// Matches every term of `search_query`, returning `None` if any term doesn't hold.
+    pub fn match_query(&self, search_query: &SearchQuery) -> Option<QueryMatch> {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let mut query_match = QueryMatch {
+            tag_indices: vec![vec![]; self.tags.len()],
+            ..QueryMatch::default()
+        };
+        for query_term in &search_query.terms {
+            let term_match = self.match_term(&matcher, &query_term.kind);
+            if query_term.is_negated {
+                if term_match.is_some() {
+                    return None;
+                }
+                continue;
+            }
+            let (score, query_field, indices) = term_match?;
+            query_match.field_rank += query_field.rank();
+            query_match.score += score;
+            let matched_indices = match query_field {
+                QueryField::Name => &mut query_match.name_indices,
+                QueryField::Source => &mut query_match.source_indices,
+                QueryField::Author => &mut query_match.author_indices,
+                QueryField::Desc => &mut query_match.desc_indices,
+                QueryField::Tag(tag_idx) => &mut query_match.tag_indices[tag_idx],
+            };
+            matched_indices.extend(indices);
+        }
+        Some(query_match)
     }
+    /// Returns the score of the field `query_term_kind` fits best, going by [`QueryField::rank`] and then
+    /// by score, and which of its characters it matched. A word that doesn't fuzzily match a field can still
+    /// match one of its words with a typo. Qualified terms only filter templates, so they score nothing.
+    fn match_term(
+        &self,
+        matcher: &SkimMatcherV2,
+        query_term_kind: &QueryTermKind,
+    ) -> Option<(i64, QueryField, Vec<usize>)> {
+        let fields = [
+            (QueryField::Name, &*self.template_name),
+            (QueryField::Source, &*self.source),
+            (QueryField::Author, &*self.author),
+            (QueryField::Desc, &*self.desc),
+        ]
+        .into_iter()
+        .chain(
+            self.tags
+                .iter()
+                .enumerate()
+                .map(|(tag_idx, tag)| (QueryField::Tag(tag_idx), &*tag.text)),
+        );
+        match query_term_kind {
+            QueryTermKind::Word(word) => {
+                let min_score = MIN_MATCH_SCORE_PER_CHAR * word.chars().count() as i64;
+                fields
+                    .filter_map(|(query_field, text)| {
+                        let (score, indices) = matcher
+                            .fuzzy_indices(text, word)
+                            .filter(|(score, _)| *score >= min_score)
+                            .or_else(|| find_typo_match(text, word))?;
+                        Some((score, query_field, indices))
+                    })
+                    .max_by_key(|(score, query_field, _)| (query_field.rank(), *score))
+            }
+            QueryTermKind::Phrase(phrase) => {
+                let phrase_len = phrase.chars().count();
+                fields
+                    .filter_map(|(query_field, text)| {
+                        let phrase_idx = find_phrase(text, phrase)?;
+                        let score = PHRASE_MATCH_SCORE_PER_CHAR * phrase_len as i64;
+                        Some((
+                            score,
+                            query_field,
+                            (phrase_idx..phrase_idx + phrase_len).collect(),
+                        ))
+                    })
+                    .max_by_key(|(score, query_field, _)| (query_field.rank(), *score))
+            }
+            QueryTermKind::Tag {
+                tag_type,
+                name,
+                version,
+            } => {
+                let tag_idx = self.tags.iter().position(|tag| {
+                    tag_type.is_none_or(|tag_type| tag.tag_type == tag_type)
+                        && tag.text.eq_ignore_ascii_case(name)
+                        && version
+                            .as_ref()
+                            .is_none_or(|version| tag.has_version(version))
+                })?;
+                let tag_text_len = self.tags[tag_idx].text.chars().count();
+                Some((0, QueryField::Tag(tag_idx), (0..tag_text_len).collect()))
+            }
+            QueryTermKind::Author(author) => self.author.eq_ignore_ascii_case(author).then(|| {
+                (
+                    0,
+                    QueryField::Author,
+                    (0..self.author.chars().count()).collect(),
+                )
+            }),
+        }
+    }
+}
+
+/// Finds a word of `text` at most one typo away from `word`, ignoring case, and returns its score and
+/// character indices. A typo is a letter added, left out, replaced or swapped with the next one.
+fn find_typo_match(text: &str, word: &str) -> Option<(i64, Vec<usize>)> {
+    let word_chars = lowercase_chars(word);
+    if word_chars.len() < MIN_TYPO_MATCH_LEN {
+        return None;
+    }
+    let text_chars = lowercase_chars(text);
+    let mut text_word_start = 0;
+    for text_word in text_chars.split(|c| !c.is_alphanumeric()) {
+        if is_within_one_typo(text_word, &word_chars) {
+            let score = TYPO_MATCH_SCORE_PER_CHAR * word_chars.len() as i64;
+            return Some((
+                score,
+                (text_word_start..text_word_start + text_word.len()).collect(),
+            ));
+        }
+        text_word_start += text_word.len() + 1;
+    }
+    None
+}
+
+/// Whether the Damerau–Levenshtein distance between `a` and `b` is at most 1
+fn is_within_one_typo(a: &[char], b: &[char]) -> bool {
+    let common_prefix_len = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&a[common_prefix_len..], &b[common_prefix_len..]);
+    match (a, b) {
+        ([], []) => true,
+        // Swapped with the next letter
+        ([a_0, a_1, a_rest @ ..], [b_0, b_1, b_rest @ ..])
+            if a_0 == b_1 && a_1 == b_0 && a_rest == b_rest =>
+        {
+            true
+        }
+        // Replaced
+        ([_, a_rest @ ..], [_, b_rest @ ..]) if a_rest == b_rest => true,
+        // Added or left out
+        ([_, a_rest @ ..], b) if a_rest == b => true,
+        (a, [_, b_rest @ ..]) if a == b_rest => true,
+        _ => false,
+    }
+}
+
+/// The characters of `text`, lowercased one to one so their indices stay those of `text`
+fn lowercase_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Returns the index of the character `phrase` starts at in `text`, ignoring case
+fn find_phrase(text: &str, phrase: &str) -> Option<usize> {
+    let text_chars = lowercase_chars(text);
+    let phrase_chars = lowercase_chars(phrase);
+    if phrase_chars.is_empty() {
+        return Some(0);
+    }
+    text_chars
+        .windows(phrase_chars.len())
+        .position(|window| window == phrase_chars)
+}
+
+/// Splits `text` into spans, styling the characters at `highlighted_indices` with `highlight_style`
+pub fn highlighted_spans<'a>(
+    text: &'a str,
+    highlighted_indices: &[usize],
+    style: Style,
+    highlight_style: Style,
+) -> Vec<Span<'a>> {
+    let mut spans = vec![];
+    let mut span_start = 0;
+    let mut is_span_highlighted = false;
+    for (char_idx, (byte_idx, _)) in text.char_indices().enumerate() {
+        let is_char_highlighted = highlighted_indices.contains(&char_idx);
+        if is_char_highlighted != is_span_highlighted {
+            if byte_idx > span_start {
+                spans.push(Span::styled(
+                    &text[span_start..byte_idx],
+                    if is_span_highlighted {
+                        highlight_style
+                    } else {
+                        style
+                    },
+                ));
+            }
+            span_start = byte_idx;
+            is_span_highlighted = is_char_highlighted;
+        }
+    }
+    if span_start < text.len() {
+        spans.push(Span::styled(
+            &text[span_start..],
+            if is_span_highlighted {
+                highlight_style
+            } else {
+                style
+            },
+        ));
+    }
+    spans
 }
 
 #[derive(Debug, Default)]
@@ -99,53 +475,81 @@ pub struct ScaffoldTabAreas {
 }
 impl ScaffoldListEntry {
     fn to_list_item(&self, is_even_item: bool, is_selected: bool) -> widgets::ListItem<'_> {
-        let (bg_color, text_color, light_text_color) = if is_selected {
-            (Color::LightYellow, Color::Black, Color::DarkGray)
+        let (bg_color, text_color, light_text_color, highlight_color) = if is_selected {
+            (
+                Color::LightYellow,
+                Color::Black,
+                Color::DarkGray,
+                Color::Red,
+            )
         } else if is_even_item {
-            (Color::Reset, Color::Reset, Color::Gray)
+            (Color::Reset, Color::Reset, Color::Gray, Color::Yellow)
         } else {
-            (Color::DarkGray, Color::Gray, Color::Gray)
+            (
+                Color::DarkGray,
+                Color::Gray,
+                Color::Gray,
+                Color::LightYellow,
+            )
         };
+        let name_style = Style::new()
+            .add_modifier(Modifier::BOLD)
+            .bg(bg_color)
+            .fg(text_color);
+        let light_style = Style::new()
+            .add_modifier(Modifier::ITALIC)
+            .bg(bg_color)
+            .fg(light_text_color);
+        let highlight = |style: Style| {
+            style
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+                .fg(highlight_color)
+        };
+        let query_match = &self.query_match;
+
+        let mut first_line_spans = highlighted_spans(
+            &self.template_name,
+            &query_match.name_indices,
+            name_style,
+            highlight(name_style),
+        );
+        first_line_spans.push(Span::styled(" by ", light_style));
+        first_line_spans.extend(highlighted_spans(
+            &self.author,
+            &query_match.author_indices,
+            light_style,
+            highlight(light_style),
+        ));
+        first_line_spans.push(Span::styled(
+            format!(" ({})", self.template_id),
+            Style::new().bg(bg_color).fg(light_text_color),
+        ));
+        first_line_spans.push(Span::styled(" from ", light_style));
+        first_line_spans.extend(highlighted_spans(
+            &self.source,
+            &query_match.source_indices,
+            light_style,
+            highlight(light_style),
+        ));
+        first_line_spans.push(Span::raw(" ".repeat(200)).bg(bg_color));
+
+        let mut desc_line_spans = highlighted_spans(
+            &self.desc,
+            &query_match.desc_indices,
+            light_style,
+            highlight(light_style),
+        );
+        desc_line_spans.push(Span::raw(" ".repeat(200)).bg(bg_color));
+
         let contents = Text::from(vec![
-            Line::from(vec![
-                Span::styled(
-                    &*self.template_name,
-                    Style::new()
-                        .add_modifier(Modifier::BOLD)
-                        .bg(bg_color)
-                        .fg(text_color),
-                ),
-                Span::styled(
-                    " by ",
-                    Style::new()
-                        .add_modifier(Modifier::ITALIC)
-                        .bg(bg_color)
-                        .fg(light_text_color),
-                ),
-                Span::styled(
-                    &*self.author,
-                    Style::new()
-                        .add_modifier(Modifier::ITALIC)
-                        .bg(bg_color)
-                        .fg(light_text_color),
-                ),
-                Span::raw(" ".repeat(200)).bg(bg_color),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    &*self.desc,
-                    Style::new()
-                        .add_modifier(Modifier::ITALIC)
-                        .bg(bg_color)
-                        .fg(light_text_color),
-                ),
-                Span::raw(" ".repeat(200)).bg(bg_color),
-            ]),
+            Line::from(first_line_spans),
+            Line::from(desc_line_spans),
             Line::from(
                 self.tags
                     .iter()
-                    .flat_map(|a| {
-                        a.to_line(bg_color)
+                    .zip(&query_match.tag_indices)
+                    .flat_map(|(tag, tag_indices)| {
+                        tag.to_line(bg_color, tag_indices)
                             .spans
                             .into_iter()
                             .chain(iter::once(Span::raw(" ").bg(bg_color)))
@@ -163,35 +567,116 @@ impl ScaffoldListEntry {
 pub enum ScaffoldTabFocus {
     #[default]
     Searchbar,
+    Sidebar,
     List,
 }
+
+impl ScaffoldTab {
+    fn render_sidebar(&mut self, area: Rect, buf: &mut Buffer, border_color: Color) {
+        let is_focused = matches!(self.focus, ScaffoldTabFocus::Sidebar);
+        let items = self.tag_facets.iter().enumerate().map(|(i, tag_facet)| {
+            let mut lines = vec![];
+            if i == 0 || self.tag_facets[i - 1].tag_type != tag_facet.tag_type {
+                lines.push(Line::styled(
+                    tag_facet.tag_type.group_name(),
+                    Style::new()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(tag_facet.tag_type.color()),
+                ));
+            }
+            let is_tag_selected = self
+                .selected_tags
+                .contains(&(tag_facet.tag_type, tag_facet.name.clone()));
+            let style = if is_focused && self.sidebar_state.selected() == Some(i) {
+                Style::new().fg(Color::Black).bg(Color::LightYellow)
+            } else if tag_facet.template_count == 0 {
+                Style::new().fg(Color::DarkGray)
+            } else {
+                Style::new()
+            };
+            lines.push(Line::styled(
+                format!(
+                    "{} {} ({})",
+                    if is_tag_selected { "[x]" } else { "[ ]" },
+                    tag_facet.name,
+                    tag_facet.template_count
+                ),
+                style,
+            ));
+            widgets::ListItem::new(lines)
+        });
+        let sidebar = widgets::List::new(items).block(
+            Block::bordered()
+                .title("Tags")
+                .title_bottom(" <ENTER> - Toggle ")
+                .border_style(Style::new().fg(border_color)),
+        );
+        StatefulWidget::render(sidebar, area, buf, &mut self.sidebar_state);
+    }
+}
+
+/// Moves the selection of a list with `len` items by one, wrapping around at either end
+fn select_wrapping(list_state: &mut ListState, len: usize, is_down: bool) {
+    if len == 0 {
+        return;
+    }
+    let selected_idx = list_state.selected().unwrap_or(0);
+    list_state.select(Some(if is_down {
+        (selected_idx + 1) % len
+    } else {
+        selected_idx.checked_sub(1).unwrap_or(len - 1)
+    }));
+}
 impl Tab for ScaffoldTab {
     fn render(&mut self, area: Rect, buf: &mut Buffer) {
-        let [searchbar_area, list_area] =
+        let [searchbar_area, mut list_area] =
             Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
-        let (searchbar_border_color, list_border_color) = if let ScaffoldTabFocus::List = self.focus
-        {
-            (Color::White, Color::Yellow)
-        } else {
-            (Color::Yellow, Color::White)
+        let (searchbar_border_color, sidebar_border_color, list_border_color) = match self.focus {
+            ScaffoldTabFocus::Searchbar => (Color::Yellow, Color::White, Color::White),
+            ScaffoldTabFocus::Sidebar => (Color::White, Color::Yellow, Color::White),
+            ScaffoldTabFocus::List => (Color::White, Color::White, Color::Yellow),
         };
         self.areas.list = list_area;
         self.areas.searchbar = searchbar_area;
 
+        let mut searchbar_block = Block::bordered()
+            .title("Find Template")
+            .border_style(Style::new().fg(searchbar_border_color));
+        if !self.stale_registries.is_empty() {
+            searchbar_block = searchbar_block.title(
+                Line::styled(
+                    format!(
+                        " Stale catalog: offline, showing cached templates from {} ",
+                        self.stale_registries.join(", ")
+                    ),
+                    Style::new().fg(Color::Black).bg(Color::Yellow),
+                )
+                .right_aligned(),
+            );
+        }
         let searchbar = widgets::Paragraph::new(visual_input_text(&mut self.searchbar_input))
             .scroll((
                 0,
                 self.searchbar_input
                     .visual_scroll(searchbar_area.width as usize) as u16,
             ))
-            .block(
-                Block::bordered()
-                    .title("Find Template")
-                    .border_style(Style::new().fg(searchbar_border_color)),
-            );
+            .block(searchbar_block);
 
         searchbar.render(searchbar_area, buf);
 
+        if self.catalog_task.is_none() && self.is_sidebar_shown {
+            let [sidebar_area, rest_area] =
+                Layout::horizontal([Constraint::Length(SIDEBAR_WIDTH), Constraint::Fill(1)])
+                    .areas(list_area);
+            self.render_sidebar(sidebar_area, buf, sidebar_border_color);
+            list_area = rest_area;
+        }
+        if self.catalog_task.is_some() {
+            let list_block = Block::bordered().border_style(Style::new().fg(list_border_color));
+            spinner_line("Loading templates...").render(list_block.inner(list_area), buf);
+            list_block.render(list_area, buf);
+            return;
+        }
         let list = widgets::List::new(self.list_data.iter().enumerate().map(|(i, list_entry)| {
             list_entry.to_list_item(
                 i % 2 == 1,
@@ -199,51 +684,85 @@ impl Tab for ScaffoldTab {
                     .selected()
                     .is_some_and(|selected_idx| i == selected_idx),
             )
-        }))
-        .block(
-            Block::bordered()
-                .title_bottom(" <ESC> - Exit | <TAB> - Switch Focus | <UP> / <DOWN> - Scroll List | <ENTER> - Select ")
-                .border_style(Style::new().fg(list_border_color)),
-        );
+        }));
+        let mut list_block = Block::bordered()
+            .title_bottom(" <ESC> - Exit | <TAB> - Switch Focus | <CTRL + T> - Toggle Tags | <UP> / <DOWN> - Scroll List | <ENTER> - Select | <D> - Details ")
+            .border_style(Style::new().fg(list_border_color));
+        if !self.selected_tags.is_empty() {
+            list_block = list_block.title(format!(
+                " Tags: {} ",
+                self.selected_tags
+                    .iter()
+                    .map(|(_, name)| &**name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let list = list.block(list_block);
         StatefulWidget::render(list, list_area, buf, &mut self.list_state)
     }
+    fn handle_start(&mut self, commands: &mut Commands) {
+        self.load_catalog(commands);
+    }
     fn handle_event(&mut self, ev: Event, commands: &mut Commands) {
         match &ev {
             Event::Key(key) => match key.code {
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.is_sidebar_shown = !self.is_sidebar_shown;
+                    if !self.is_sidebar_shown && matches!(self.focus, ScaffoldTabFocus::Sidebar) {
+                        self.focus = ScaffoldTabFocus::List;
+                    }
+                    return;
+                }
                 KeyCode::Tab => {
                     self.focus = match self.focus {
-                        ScaffoldTabFocus::Searchbar => ScaffoldTabFocus::List,
+                        ScaffoldTabFocus::Searchbar if self.is_sidebar_shown => {
+                            ScaffoldTabFocus::Sidebar
+                        }
+                        ScaffoldTabFocus::Searchbar | ScaffoldTabFocus::Sidebar => {
+                            ScaffoldTabFocus::List
+                        }
                         ScaffoldTabFocus::List => ScaffoldTabFocus::Searchbar,
                     };
                 }
-                KeyCode::Up => match self.focus {
-                    ScaffoldTabFocus::List => {
-                        if self.list_state.selected() != Some(0) {
-                            self.list_state.select_previous();
-                        } else {
-                            self.list_state.select(Some(self.list_data.len() - 1));
+                KeyCode::Up | KeyCode::Down => {
+                    let is_down = key.code == KeyCode::Down;
+                    match self.focus {
+                        ScaffoldTabFocus::List => {
+                            select_wrapping(&mut self.list_state, self.list_data.len(), is_down);
                         }
-                    }
-                    ScaffoldTabFocus::Searchbar => {}
-                },
-                KeyCode::Down => match self.focus {
-                    ScaffoldTabFocus::List => {
-                        if self.list_state.selected() != Some(self.list_data.len() - 1) {
-                            self.list_state.select_next();
-                        } else {
-                            self.list_state.select_first();
+                        ScaffoldTabFocus::Sidebar => {
+                            select_wrapping(
+                                &mut self.sidebar_state,
+                                self.tag_facets.len(),
+                                is_down,
+                            );
                         }
+                        ScaffoldTabFocus::Searchbar => {}
                     }
-                    ScaffoldTabFocus::Searchbar => {}
-                },
+                }
+                KeyCode::Char(' ') if matches!(self.focus, ScaffoldTabFocus::Sidebar) => {
+                    self.toggle_selected_tag();
+                }
+                KeyCode::Char('d') if matches!(self.focus, ScaffoldTabFocus::List) => {
+                    if let Some(template_info) = self.selected_template_info(commands) {
+                        commands.cache_current_tab();
+                        commands.switch_tab_to(|commands| {
+                            TemplateDetailTab::new(template_info, commands)
+                        });
+                    }
+                }
                 KeyCode::Enter => match self.focus {
                     ScaffoldTabFocus::List => {
-                        commands.cache_current_tab();
-                        commands.switch_tab_to(ProjectInitTab::new(
-                            self.list_data[self.list_state.selected().unwrap()]
-                                .template_id
-                                .clone(),
-                        ));
+                        if let Some(template_info) = self.selected_template_info(commands) {
+                            commands.cache_current_tab();
+                            commands.switch_tab_to(|commands| {
+                                ProjectInitTab::new(template_info, commands)
+                            });
+                        }
+                    }
+                    ScaffoldTabFocus::Sidebar => {
+                        self.toggle_selected_tag();
                     }
                     ScaffoldTabFocus::Searchbar => {
                         self.focus = ScaffoldTabFocus::List;
@@ -264,7 +783,198 @@ impl Tab for ScaffoldTab {
                     self.update_list();
                 }
             }
-            ScaffoldTabFocus::List => {}
+            ScaffoldTabFocus::Sidebar | ScaffoldTabFocus::List => {}
         }
     }
+    fn handle_task_event(&mut self, task_event: TaskEvent, commands: &mut Commands) {
+        if let TaskEvent::CatalogLoaded(result) = task_event {
+            self.catalog_task = None;
+            self.stale_registries = stale_registry_names();
+            self.update_list();
+            if let Err(err) = result {
+                commands.show_error(
+                    ErrorDialog::new("Could not load the template catalog", &err).with_retry(),
+                );
+            }
+        }
+    }
+    fn handle_error_action(&mut self, error_action: ErrorAction, commands: &mut Commands) {
+        if let ErrorAction::Retry = error_action {
+            self.load_catalog(commands);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fuzzy_matcher::skim::SkimMatcherV2;
+    use ratatui::prelude::*;
+
+    use super::{
+        QueryField, ScaffoldListEntry, find_phrase, highlighted_spans, is_within_one_typo,
+    };
+    use crate::tabs::{
+        query::{QueryTermKind, SearchQuery},
+        tag::{Tag, TagType},
+    };
+
+    fn entry(name: &str, desc: &str, tags: &[&str]) -> ScaffoldListEntry {
+        ScaffoldListEntry::new(
+            name.into(),
+            name.to_lowercase().replace(' ', "-").into(),
+            "scaffy".into(),
+            "tester".into(),
+            desc.into(),
+            tags.iter()
+                .map(|tag| Tag::new((*tag).into(), TagType::Misc, None))
+                .collect(),
+        )
+    }
+
+    /// Names of the entries matching `query`, from the best match to the worst
+    fn ranked_names(entries: &[ScaffoldListEntry], query: &str) -> Vec<String> {
+        let search_query = SearchQuery::parse(query);
+        let mut matches = entries
+            .iter()
+            .filter_map(|entry| Some((entry.match_query(&search_query)?.rank(), entry)))
+            .collect::<Vec<_>>();
+        matches.sort_by(|(rank_1, _), (rank_2, _)| rank_2.cmp(rank_1));
+        matches
+            .into_iter()
+            .map(|(_, entry)| entry.template_name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn ranks_name_matches_above_tag_and_description_matches() {
+        let entries = [
+            entry("Static Site", "Web server for static sites", &[]),
+            entry("Rust CLI", "Command line app", &["weekly-brief"]),
+            entry("Weather Bot", "Forecasts", &[]),
+        ];
+        // The matches score the other way around, yet the name match comes first, then the tag match
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let word = QueryTermKind::Word("web".into());
+        let term_match = |entry: &ScaffoldListEntry| {
+            let (score, query_field, _) = entry.match_term(&matcher, &word).unwrap();
+            (query_field, score)
+        };
+        let (desc_field, desc_score) = term_match(&entries[0]);
+        let (tag_field, tag_score) = term_match(&entries[1]);
+        let (name_field, name_score) = term_match(&entries[2]);
+        assert_eq!(
+            [desc_field, tag_field, name_field],
+            [QueryField::Desc, QueryField::Tag(0), QueryField::Name]
+        );
+        assert!(name_score < tag_score && tag_score < desc_score);
+        assert_eq!(
+            ranked_names(&entries, "web"),
+            ["Weather Bot", "Rust CLI", "Static Site"]
+        );
+        assert_eq!(ranked_names(&entries, "\"web\""), ["Static Site"]);
+    }
+
+    #[test]
+    fn tolerates_left_out_letters_but_not_scattered_ones() {
+        let entries = [entry(
+            "Wasm Starter",
+            "Rust compiled to WebAssembly",
+            &["rust"],
+        )];
+        assert_eq!(ranked_names(&entries, "wsm"), ["Wasm Starter"]);
+        assert_eq!(ranked_names(&entries, "strtr rst"), ["Wasm Starter"]);
+        // Every letter is in the description, but too far apart to count
+        assert!(ranked_names(&entries, "uiby").is_empty());
+        assert!(ranked_names(&entries, "wasm -rust").is_empty());
+    }
+
+    #[test]
+    fn tolerates_one_typo_per_word() {
+        let entries = [
+            entry("Rust CLI", "Command line app", &["rust"]),
+            entry("Python Script", "Automation", &["python"]),
+        ];
+        // Swapped letters
+        assert_eq!(ranked_names(&entries, "rsut"), ["Rust CLI"]);
+        assert_eq!(ranked_names(&entries, "pyhton"), ["Python Script"]);
+        // A replaced letter
+        assert_eq!(ranked_names(&entries, "rist"), ["Rust CLI"]);
+        assert_eq!(ranked_names(&entries, "comnand"), ["Rust CLI"]);
+        // Only one typo per word, and never in words too short to tell apart
+        assert!(ranked_names(&entries, "rsit").is_empty());
+        assert_eq!(ranked_names(&entries, "rsut -pyhton cli"), ["Rust CLI"]);
+        assert!(ranked_names(&entries, "xi").is_empty());
+
+        // A typo match is highlighted as the whole word it matched
+        let query_match = entries[0]
+            .match_query(&SearchQuery::parse("comnand"))
+            .unwrap();
+        assert_eq!(query_match.desc_indices, (0..7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn measures_one_typo() {
+        let within_one_typo = |a: &str, b: &str| {
+            is_within_one_typo(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!(within_one_typo("rust", "rust"));
+        assert!(within_one_typo("rust", "rsut"));
+        assert!(within_one_typo("rust", "rut"));
+        assert!(within_one_typo("rust", "rusty"));
+        assert!(within_one_typo("rust", "trust"));
+        assert!(!within_one_typo("rust", "srut"));
+        assert!(!within_one_typo("rust", "rsutt"));
+        assert!(!within_one_typo("rust", "ru"));
+    }
+
+    #[test]
+    fn finds_phrases_by_character_index() {
+        assert_eq!(find_phrase("Ünïcode Café App", "café app"), Some(8));
+        assert_eq!(find_phrase("Ünïcode Café App", "ÜNÏ"), Some(0));
+        assert_eq!(find_phrase("Ünïcode Café App", "cafe"), None);
+        assert_eq!(find_phrase("short", "longer than it"), None);
+        assert_eq!(find_phrase("anything", ""), Some(0));
+    }
+
+    #[test]
+    fn highlights_matched_characters_of_multi_byte_names() {
+        let style = Style::new();
+        let highlight_style = Style::new().bold();
+        let spans = |text: &'static str, highlighted_indices: &[usize]| {
+            highlighted_spans(text, highlighted_indices, style, highlight_style)
+                .into_iter()
+                .map(|span| (span.content.into_owned(), span.style == highlight_style))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            spans("日本語 app", &[1, 2]),
+            [
+                ("日".to_owned(), false),
+                ("本語".to_owned(), true),
+                (" app".to_owned(), false)
+            ]
+        );
+        assert_eq!(
+            spans("héllo", &[0, 4]),
+            [
+                ("h".to_owned(), true),
+                ("éll".to_owned(), false),
+                ("o".to_owned(), true)
+            ]
+        );
+        assert_eq!(spans("plain", &[]), [("plain".to_owned(), false)]);
+
+        // The indices a match reports are character indices, which line up with the highlighted text
+        let name_entry = entry("Café Ünïcode", "", &[]);
+        let query_match = name_entry
+            .match_query(&SearchQuery::parse("\"ünï\""))
+            .unwrap();
+        assert_eq!(
+            spans("Café Ünïcode", &query_match.name_indices)[1],
+            ("Ünï".to_owned(), true)
+        );
+    }
 }
@@ -1,18 +1,44 @@
 use ratatui::prelude::*;
+use serde::Serialize;
 
-use crate::template_info::{ArcStr, TemplateInfoTags};
+use crate::{
+    tabs::search::highlighted_spans,
+    template_info::{ArcStr, TemplateInfoTags},
+};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TagType {
     Language,
     Framework,
     Library,
     Misc,
 }
+impl TagType {
+    pub fn color(&self) -> Color {
+        match self {
+            TagType::Framework => Color::Red,
+            TagType::Language => Color::Blue,
+            TagType::Library => Color::Green,
+            TagType::Misc => Color::Gray,
+        }
+    }
+    /// The heading of the tags of this type in the tag sidebar
+    pub fn group_name(&self) -> &'static str {
+        match self {
+            TagType::Language => "Languages",
+            TagType::Framework => "Frameworks",
+            TagType::Library => "Libraries",
+            TagType::Misc => "Misc",
+        }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Tag {
+    #[serde(rename = "name")]
     pub text: ArcStr,
+    #[serde(rename = "type")]
     pub tag_type: TagType,
     pub version: Option<ArcStr>,
 }
@@ -24,27 +50,42 @@ impl Tag {
             version,
         }
     }
+    /// Whether the tag's version is `version` or a more specific one, e.g. `19.1` for `19`
+    pub fn has_version(&self, version: &str) -> bool {
+        self.version.as_ref().is_some_and(|tag_version| {
+            tag_version
+                .strip_prefix(version)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
     pub fn tag_col(&self) -> Color {
-        match self.tag_type {
-            TagType::Framework => Color::Red,
-            TagType::Language => Color::Blue,
-            TagType::Library => Color::Green,
-            TagType::Misc => Color::Gray,
-        }
+        self.tag_type.color()
     }
-    pub fn to_line<'a>(&'a self, bg_color: Color) -> Line<'a> {
-        Line::from(vec![
-            Span::styled("▐", Style::new().fg(self.tag_col()).bg(bg_color)),
-            Span::styled(&*self.text, Style::new().bg(self.tag_col())),
-            Span::styled(
-                self.version
-                    .as_ref()
-                    .map(|version| format!("@{version}"))
-                    .unwrap_or(String::new()),
-                Style::new().bg(self.tag_col()),
-            ),
-            Span::styled("▌", Style::new().fg(self.tag_col()).bg(bg_color)),
-        ])
+    /// Renders the tag as a pill, underlining the characters of its text at `highlighted_indices`
+    pub fn to_line<'a>(&'a self, bg_color: Color, highlighted_indices: &[usize]) -> Line<'a> {
+        let text_style = Style::new().bg(self.tag_col());
+        let mut spans = vec![Span::styled(
+            "▐",
+            Style::new().fg(self.tag_col()).bg(bg_color),
+        )];
+        spans.extend(highlighted_spans(
+            &self.text,
+            highlighted_indices,
+            text_style,
+            text_style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ));
+        spans.push(Span::styled(
+            self.version
+                .as_ref()
+                .map(|version| format!("@{version}"))
+                .unwrap_or_default(),
+            text_style,
+        ));
+        spans.push(Span::styled(
+            "▌",
+            Style::new().fg(self.tag_col()).bg(bg_color),
+        ));
+        Line::from(spans)
     }
 }
 
@@ -76,3 +117,20 @@ pub fn parse_template_info_tags(template_info_tags: &TemplateInfoTags) -> Vec<Ta
     .flatten()
     .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Tag, TagType};
+
+    #[test]
+    fn matches_versions_by_their_leading_parts() {
+        let react = Tag::new("react".into(), TagType::Framework, Some("19.1.0".into()));
+        assert!(react.has_version("19"));
+        assert!(react.has_version("19.1"));
+        assert!(react.has_version("19.1.0"));
+        assert!(!react.has_version("1"));
+        assert!(!react.has_version("19.10"));
+        assert!(!react.has_version("19.1.0.1"));
+        assert!(!Tag::new("rust".into(), TagType::Language, None).has_version("1"));
+    }
+}
@@ -0,0 +1,336 @@
+use ratatui::{
+    crossterm::event::{Event, KeyCode},
+    prelude::*,
+    widgets::{self, Block, Wrap},
+};
+use smol::Task;
+
+use crate::{
+    app::{Commands, TaskEvent},
+    error_dialog::{ErrorAction, ErrorDialog},
+    markdown::markdown_to_text,
+    progress_widget::spinner_line,
+    tabs::{
+        Tab,
+        project_init::ProjectInitTab,
+        tag::{Tag, parse_template_info_tags},
+    },
+    template_info::{
+        ArcStr, TemplateInfo, TemplateStructure, TemplateStructureDirEntryData,
+        get_template_file_contents, load_template_structure,
+    },
+};
+
+/// Names a template's README can have at its root, from the most to the least preferred, ignoring case
+const README_NAMES: [&str; 3] = ["readme.md", "readme.markdown", "readme"];
+
+/// Shows a template's details and README, so it can be looked at before being picked
+pub struct TemplateDetailTab {
+    template_info: TemplateInfo,
+    tags: Vec<Tag>,
+    /// Counted from the template's structure, which is loaded in the background; `None` while loading
+    file_count: Option<Result<usize, String>>,
+    readme: ReadmeState,
+    details_task: Option<Task<()>>,
+    scroll_pos: u16,
+}
+
+enum ReadmeState {
+    Loading,
+    /// The template has no README at its root
+    Missing,
+    Loaded(Text<'static>),
+    Failed(String),
+}
+
+impl TemplateDetailTab {
+    pub fn new(template_info: TemplateInfo, commands: &Commands) -> Self {
+        let mut template_detail_tab = TemplateDetailTab {
+            tags: parse_template_info_tags(&template_info.tags),
+            template_info,
+            file_count: None,
+            readme: ReadmeState::Loading,
+            details_task: None,
+            scroll_pos: 0,
+        };
+        template_detail_tab.load_details(commands);
+        template_detail_tab
+    }
+    /// Fetches the template's structure, then its README if it has one.
+    fn load_details(&mut self, commands: &Commands) {
+        let template_id = self.template_info.id.clone();
+        self.file_count = None;
+        self.readme = ReadmeState::Loading;
+        self.details_task = Some(commands.spawn(|task_events| async move {
+            let result = load_template_structure(template_id.clone()).await;
+            let readme_path = result.as_ref().ok().and_then(find_readme_path);
+            task_events.send(TaskEvent::TemplateStructureLoaded {
+                template_id: template_id.clone(),
+                result,
+            });
+            if let Some(readme_path) = readme_path {
+                let result = get_template_file_contents(&template_id, &readme_path)
+                    .await
+                    .map(|contents| String::from_utf8_lossy(&contents).into_owned());
+                task_events.send(TaskEvent::ReadmeLoaded {
+                    template_id,
+                    result,
+                });
+            }
+        }));
+    }
+    fn header_lines(&self) -> Vec<Line<'_>> {
+        let light_style = Style::new().add_modifier(Modifier::ITALIC).fg(Color::Gray);
+        let template_info = &self.template_info;
+        let tags_line = if self.tags.is_empty() {
+            Line::styled("No tags", light_style)
+        } else {
+            Line::from(
+                self.tags
+                    .iter()
+                    .flat_map(|tag| {
+                        tag.to_line(Color::Reset, &[])
+                            .spans
+                            .into_iter()
+                            .chain([Span::raw(" ")])
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let file_count_line = match &self.file_count {
+            None => spinner_line("Loading the template's files..."),
+            Some(Ok(1)) => Line::raw("1 file"),
+            Some(Ok(file_count)) => Line::raw(format!("{file_count} files")),
+            Some(Err(err)) => Line::styled(
+                format!("Could not load the template's files: {err}"),
+                Style::new().fg(Color::Red),
+            ),
+        };
+        vec![
+            Line::from(vec![
+                Span::styled("by ", light_style),
+                Span::styled(&*template_info.author, light_style),
+                Span::styled(format!(" ({})", template_info.id), light_style),
+                Span::styled(format!(" from {}", template_info.registry), light_style),
+            ]),
+            Line::raw(&*template_info.description),
+            tags_line,
+            file_count_line,
+            Line::default(),
+        ]
+    }
+}
+
+/// Finds the README at the root of a template, if it has one
+fn find_readme_path(template_structure: &TemplateStructure) -> Option<ArcStr> {
+    README_NAMES.into_iter().find_map(|readme_name| {
+        template_structure
+            .iter()
+            .find(|(name, entry)| {
+                name.eq_ignore_ascii_case(readme_name)
+                    && matches!(entry, TemplateStructureDirEntryData::File { .. })
+            })
+            .map(|(name, _)| name.clone())
+    })
+}
+
+/// Counts the files and symlinks in a template, including ones that are only generated under some condition
+fn count_files(template_structure: &TemplateStructure) -> usize {
+    template_structure
+        .values()
+        .map(|entry| match entry {
+            TemplateStructureDirEntryData::Folder { children, .. } => count_files(children),
+            TemplateStructureDirEntryData::File { .. }
+            | TemplateStructureDirEntryData::Symlink { .. } => 1,
+            TemplateStructureDirEntryData::EmptyFolder { .. } => 0,
+        })
+        .sum()
+}
+
+impl Tab for TemplateDetailTab {
+    fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(format!(" {} ", self.template_info.name))
+            .title_bottom(" <ESC> - Back | <ENTER> - Use Template | <UP> / <DOWN> - Scroll ");
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let mut lines = self.header_lines();
+        lines.push(Line::styled(
+            "─".repeat(inner_area.width as usize),
+            Style::new().fg(Color::DarkGray),
+        ));
+        match &self.readme {
+            ReadmeState::Loading => lines.push(spinner_line("Loading the README...")),
+            ReadmeState::Missing => lines.push(Line::styled(
+                "This template has no README.",
+                Style::new().add_modifier(Modifier::ITALIC).fg(Color::Gray),
+            )),
+            ReadmeState::Loaded(readme) => lines.extend(readme.lines.iter().cloned()),
+            ReadmeState::Failed(err) => lines.push(Line::styled(
+                format!("Could not load the README: {err}"),
+                Style::new().fg(Color::Red),
+            )),
+        }
+        let details = widgets::Paragraph::new(lines).wrap(Wrap { trim: false });
+        let max_scroll_pos =
+            (details.line_count(inner_area.width) as u16).saturating_sub(inner_area.height);
+        let scroll_pos = self.scroll_pos.min(max_scroll_pos);
+        details.scroll((scroll_pos, 0)).render(inner_area, buf);
+        self.scroll_pos = scroll_pos;
+    }
+    fn handle_event(&mut self, ev: Event, commands: &mut Commands) {
+        let Event::Key(key) = ev else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => {
+                commands.switch_tab_to_cached();
+            }
+            KeyCode::Enter => {
+                let template_info = self.template_info.clone();
+                commands.switch_tab_to(|commands| ProjectInitTab::new(template_info, commands));
+            }
+            KeyCode::Up => {
+                self.scroll_pos = self.scroll_pos.saturating_sub(1);
+            }
+            KeyCode::Down => {
+                self.scroll_pos = self.scroll_pos.saturating_add(1);
+            }
+            KeyCode::PageUp => {
+                self.scroll_pos = self.scroll_pos.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                self.scroll_pos = self.scroll_pos.saturating_add(10);
+            }
+            _ => {}
+        }
+    }
+    fn handle_task_event(&mut self, task_event: TaskEvent, commands: &mut Commands) {
+        match task_event {
+            TaskEvent::TemplateStructureLoaded {
+                template_id,
+                result,
+            } if template_id == self.template_info.id => match result {
+                Ok(template_structure) => {
+                    self.file_count = Some(Ok(count_files(&template_structure)));
+                    if find_readme_path(&template_structure).is_none() {
+                        self.readme = ReadmeState::Missing;
+                        self.details_task = None;
+                    }
+                }
+                Err(err) => {
+                    self.details_task = None;
+                    self.file_count = Some(Err(format!("{err:#}")));
+                    self.readme = ReadmeState::Failed(format!("{err:#}"));
+                    commands.show_error(
+                        ErrorDialog::new("Could not load the template's details", &err)
+                            .with_retry(),
+                    );
+                }
+            },
+            TaskEvent::ReadmeLoaded {
+                template_id,
+                result,
+            } if template_id == self.template_info.id => {
+                self.details_task = None;
+                self.readme = match result {
+                    Ok(readme) => ReadmeState::Loaded(markdown_to_text(&readme)),
+                    Err(err) => ReadmeState::Failed(format!("{err:#}")),
+                };
+            }
+            _ => {}
+        }
+    }
+    fn handle_error_action(&mut self, error_action: ErrorAction, commands: &mut Commands) {
+        match error_action {
+            ErrorAction::Retry => self.load_details(commands),
+            ErrorAction::Back | ErrorAction::Quit => commands.switch_tab_to_cached(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{count_files, find_readme_path};
+    use crate::template_info::{TemplateStructure, TemplateStructureDirEntryData};
+
+    fn file() -> TemplateStructureDirEntryData {
+        TemplateStructureDirEntryData::File {
+            inject_project_info: false,
+            condition: None,
+            mode: None,
+        }
+    }
+
+    fn folder(children: TemplateStructure) -> TemplateStructureDirEntryData {
+        TemplateStructureDirEntryData::Folder {
+            inject_project_info: false,
+            children,
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn counts_files_and_symlinks_in_every_folder() {
+        let template_structure = HashMap::from([
+            ("README.md".into(), file()),
+            (
+                "src".into(),
+                folder(HashMap::from([
+                    ("main.rs".into(), file()),
+                    (
+                        "nested".into(),
+                        folder(HashMap::from([("lib.rs".into(), file())])),
+                    ),
+                ])),
+            ),
+            (
+                "assets".into(),
+                TemplateStructureDirEntryData::EmptyFolder {
+                    inject_project_info: false,
+                    condition: None,
+                },
+            ),
+            (
+                "latest".into(),
+                TemplateStructureDirEntryData::Symlink {
+                    inject_project_info: false,
+                    target: "src/main.rs".into(),
+                    condition: Some("FLAG".into()),
+                },
+            ),
+        ]);
+        assert_eq!(count_files(&template_structure), 4);
+        assert_eq!(count_files(&HashMap::new()), 0);
+    }
+
+    #[test]
+    fn prefers_markdown_readmes_at_the_root() {
+        let template_structure = |names: &[&str]| -> TemplateStructure {
+            names.iter().map(|name| ((*name).into(), file())).collect()
+        };
+        assert_eq!(
+            find_readme_path(&template_structure(&["README", "Readme.md", "main.rs"])).as_deref(),
+            Some("Readme.md")
+        );
+        assert_eq!(
+            find_readme_path(&template_structure(&["readme", "README.markdown"])).as_deref(),
+            Some("README.markdown")
+        );
+        assert_eq!(
+            find_readme_path(&template_structure(&["README"])).as_deref(),
+            Some("README")
+        );
+        assert_eq!(find_readme_path(&template_structure(&["README.txt"])), None);
+
+        // A folder or a README below the root doesn't count
+        let nested = HashMap::from([
+            ("README.md".into(), folder(HashMap::new())),
+            ("docs".into(), folder(template_structure(&["README.md"]))),
+        ]);
+        assert_eq!(find_readme_path(&nested), None);
+    }
+}
@@ -0,0 +1,231 @@
+use ratatui::{
+    crossterm::event::{Event, KeyCode},
+    prelude::*,
+    widgets::{self, Block},
+};
+use tui_input::{Input, backend::crossterm::EventHandler};
+
+use crate::{
+    input_widget::visual_input_text,
+    template_vars::{TemplateVariable, TemplateVariableKind, VariableValue, VariableValues},
+};
+
+enum VariableFieldState {
+    Text(Input),
+    Bool(bool),
+    Choice(usize),
+}
+
+struct VariableField {
+    variable: TemplateVariable,
+    state: VariableFieldState,
+    error: Option<String>,
+}
+impl VariableField {
+    fn new(variable: &TemplateVariable) -> Self {
+        let state = match (&variable.kind, variable.default_value()) {
+            (TemplateVariableKind::Bool { .. }, Some(VariableValue::Bool(default))) => {
+                VariableFieldState::Bool(default)
+            }
+            (TemplateVariableKind::Bool { .. }, _) => VariableFieldState::Bool(false),
+            (TemplateVariableKind::Choice { options, .. }, default) => VariableFieldState::Choice(
+                default
+                    .and_then(|default| {
+                        options
+                            .iter()
+                            .position(|option| default.to_string() == **option)
+                    })
+                    .unwrap_or(0),
+            ),
+            (_, default) => VariableFieldState::Text(Input::new(
+                default
+                    .map(|default| default.to_string())
+                    .unwrap_or_default(),
+            )),
+        };
+        VariableField {
+            variable: variable.clone(),
+            state,
+            error: None,
+        }
+    }
+    fn value(&self) -> Result<VariableValue, String> {
+        let input = match &self.state {
+            VariableFieldState::Text(input) => input.value().to_owned(),
+            VariableFieldState::Bool(value) => value.to_string(),
+            VariableFieldState::Choice(option_idx) => match &self.variable.kind {
+                TemplateVariableKind::Choice { options, .. } => options
+                    .get(*option_idx)
+                    .map(|option| option.to_string())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            },
+        };
+        self.variable
+            .parse_value(&input)
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// A form with one field per template variable, typed according to the variable's kind
+pub struct VariableForm {
+    fields: Vec<VariableField>,
+    focus: usize,
+}
+impl VariableForm {
+    pub fn new(variables: &[TemplateVariable]) -> Self {
+        VariableForm {
+            fields: variables.iter().map(VariableField::new).collect(),
+            focus: 0,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+    /// Validates every field, returning the values if all of them are valid and otherwise
+    /// showing the errors and focusing the first invalid field.
+    pub fn validate(&mut self) -> Option<VariableValues> {
+        let mut variable_values = VariableValues::new();
+        let mut first_invalid_idx = None;
+        for (i, field) in self.fields.iter_mut().enumerate() {
+            match field.value() {
+                Ok(value) => {
+                    field.error = None;
+                    variable_values.insert(field.variable.name.clone(), value);
+                }
+                Err(err) => {
+                    field.error = Some(err);
+                    first_invalid_idx.get_or_insert(i);
+                }
+            }
+        }
+        match first_invalid_idx {
+            Some(idx) => {
+                self.focus = idx;
+                None
+            }
+            None => Some(variable_values),
+        }
+    }
+    pub fn handle_event(&mut self, ev: &Event) {
+        if self.fields.is_empty() {
+            return;
+        }
+        if let Event::Key(key_ev) = ev {
+            match key_ev.code {
+                KeyCode::Up => {
+                    self.focus = self.focus.checked_sub(1).unwrap_or(self.fields.len() - 1);
+                    return;
+                }
+                KeyCode::Down | KeyCode::Tab => {
+                    self.focus = (self.focus + 1) % self.fields.len();
+                    return;
+                }
+                _ => {}
+            }
+        }
+        let field = &mut self.fields[self.focus];
+        match &mut field.state {
+            VariableFieldState::Text(input) => {
+                input.handle_event(ev);
+            }
+            VariableFieldState::Bool(value) => {
+                if let Event::Key(key_ev) = ev
+                    && matches!(
+                        key_ev.code,
+                        KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right
+                    )
+                {
+                    *value = !*value;
+                }
+            }
+            VariableFieldState::Choice(option_idx) => {
+                let option_count = match &field.variable.kind {
+                    TemplateVariableKind::Choice { options, .. } => options.len().max(1),
+                    _ => 1,
+                };
+                if let Event::Key(key_ev) = ev {
+                    match key_ev.code {
+                        KeyCode::Left => {
+                            *option_idx = option_idx.checked_sub(1).unwrap_or(option_count - 1);
+                        }
+                        KeyCode::Right | KeyCode::Char(' ') => {
+                            *option_idx = (*option_idx + 1) % option_count;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let [title_area, fields_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        Text::styled(
+            "Template Options - <SPACE> / <LEFT> / <RIGHT> to change toggles and choices",
+            Style::new().add_modifier(Modifier::BOLD),
+        )
+        .render(title_area, buf);
+
+        let visible_field_count = (fields_area.height / 3).max(1) as usize;
+        let first_visible_idx = (self.focus + 1).saturating_sub(visible_field_count);
+        let field_areas = Layout::vertical(
+            self.fields
+                .iter()
+                .skip(first_visible_idx)
+                .take(visible_field_count)
+                .map(|_| Constraint::Length(3)),
+        )
+        .split(fields_area);
+        for ((i, field), field_area) in self
+            .fields
+            .iter_mut()
+            .enumerate()
+            .skip(first_visible_idx)
+            .zip(field_areas.iter())
+        {
+            let is_focused = i == self.focus;
+            let contents = match &mut field.state {
+                VariableFieldState::Text(input) if is_focused => visual_input_text(input),
+                VariableFieldState::Text(input) => input.value().to_owned(),
+                VariableFieldState::Bool(value) => {
+                    if *value {
+                        "[x] Yes".to_owned()
+                    } else {
+                        "[ ] No".to_owned()
+                    }
+                }
+                VariableFieldState::Choice(option_idx) => match &field.variable.kind {
+                    TemplateVariableKind::Choice { options, .. } => format!(
+                        "< {} >",
+                        options.get(*option_idx).map(|s| &**s).unwrap_or_default()
+                    ),
+                    _ => String::new(),
+                },
+            };
+            let mut block = Block::bordered()
+                .title(field.variable.prompt().to_owned())
+                .border_style(Style::new().fg(if is_focused {
+                    Color::Yellow
+                } else {
+                    Color::White
+                }));
+            if let Some(err) = &field.error {
+                block = block.title_bottom(Line::styled(
+                    format!(" {err} "),
+                    Style::new().fg(Color::Red),
+                ));
+            }
+            let scroll = match &field.state {
+                VariableFieldState::Text(input) => {
+                    input.visual_scroll(field_area.width.saturating_sub(2) as usize) as u16
+                }
+                _ => 0,
+            };
+            widgets::Paragraph::new(contents)
+                .scroll((0, scroll))
+                .block(block)
+                .render(*field_area, buf);
+        }
+    }
+}
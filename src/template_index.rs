@@ -0,0 +1,198 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre;
+
+use crate::{
+    scaffold::{symlink_escapes_root, symlink_passes_through_symlink},
+    template_info::{FileMode, TemplateInfo, TemplateStructure, TemplateStructureDirEntryData},
+    template_vars::Condition,
+};
+
+/// Name of the folder in a templates directory that holds each template's structure index
+pub const TEMPLATE_CONTENTS_DIR_NAME: &str = "__scaffy_template_contents";
+/// Every placeholder starts with this, so its presence marks an entry as needing project info injected
+pub const SCAFFY_MARKER: &[u8] = b"@@SCAFFY_";
+
+/// Builds the structure index of the template rooted at `template_dir`.
+///
+/// Symlinks are recorded as links rather than followed, and must have a relative target inside the template
+/// that doesn't go through another symlink.
+pub fn index_template(template_dir: &Path) -> eyre::Result<TemplateStructure> {
+    let mut symlinks = vec![];
+    let template_structure = index_dir(template_dir, Path::new(""), &mut symlinks)?;
+    let symlink_paths: HashSet<&Path> = symlinks.iter().map(|(path, _)| &**path).collect();
+    for (relative_path, target) in &symlinks {
+        if symlink_passes_through_symlink(relative_path, target, &symlink_paths) {
+            eyre::bail!(
+                "the symlink {} points to {}, which goes through another symlink of the template; only targets that don't are allowed",
+                relative_path.display(),
+                target.display()
+            );
+        }
+    }
+    Ok(template_structure)
+}
+
+/// Indexes `dir`, adding the path and target of each symlink in it to `symlinks`
+fn index_dir(
+    dir: &Path,
+    relative_dir: &Path,
+    symlinks: &mut Vec<(PathBuf, PathBuf)>,
+) -> eyre::Result<TemplateStructure> {
+    let mut structure = HashMap::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let file_type = dir_entry.file_type()?;
+        let dir_entry_name = dir_entry.file_name().to_string_lossy().into_owned();
+        if file_type.is_symlink() {
+            let relative_path = relative_dir.join(&dir_entry_name);
+            let target = fs::read_link(dir_entry.path())?;
+            if symlink_escapes_root(&relative_path, &target) {
+                eyre::bail!(
+                    "the symlink {} points to {}, which is outside of the template; only relative targets inside it are allowed",
+                    relative_path.display(),
+                    target.display()
+                );
+            }
+            let Some(target) = target.to_str() else {
+                eyre::bail!(
+                    "the target of the symlink {} is not valid UTF-8",
+                    relative_path.display()
+                );
+            };
+            symlinks.push((relative_path.clone(), PathBuf::from(target)));
+            structure.insert(
+                dir_entry_name.as_str().into(),
+                TemplateStructureDirEntryData::Symlink {
+                    inject_project_info: dir_entry_name.contains("@@SCAFFY_")
+                        || target.contains("@@SCAFFY_"),
+                    target: target.replace('\\', "/").into(),
+                    condition: None,
+                },
+            );
+        } else if file_type.is_dir() {
+            let children = index_dir(
+                &dir_entry.path(),
+                &relative_dir.join(&dir_entry_name),
+                symlinks,
+            )?;
+            let inject_project_info = dir_entry_name.contains("@@SCAFFY_");
+            let dir_entry_data = if children.is_empty() {
+                TemplateStructureDirEntryData::EmptyFolder {
+                    inject_project_info,
+                    condition: None,
+                }
+            } else {
+                TemplateStructureDirEntryData::Folder {
+                    inject_project_info,
+                    children,
+                    condition: None,
+                }
+            };
+            structure.insert(dir_entry_name.as_str().into(), dir_entry_data);
+        } else if file_type.is_file() {
+            let file_contents = fs::read(dir_entry.path())?;
+            let mode = file_mode(&dir_entry.metadata()?).filter(FileMode::is_executable);
+            structure.insert(
+                dir_entry_name.as_str().into(),
+                TemplateStructureDirEntryData::File {
+                    inject_project_info: str::from_utf8(&file_contents).is_ok()
+                        && file_contents
+                            .windows(SCAFFY_MARKER.len())
+                            .any(|window| window == SCAFFY_MARKER),
+                    condition: None,
+                    mode,
+                },
+            );
+        }
+    }
+    Ok(structure)
+}
+
+/// Copies the `conditions` declared in `templates.json` onto the entries they apply to,
+/// failing if an entry doesn't exist or a condition refers to an undeclared variable.
+pub fn apply_conditions(
+    structure: &mut TemplateStructure,
+    template_info: &TemplateInfo,
+) -> eyre::Result<()> {
+    for (entry_path, condition) in &template_info.conditions {
+        for variable_name in Condition::parse(condition)?.variable_names() {
+            if !template_info
+                .variables
+                .iter()
+                .any(|variable| &*variable.name == variable_name)
+            {
+                eyre::bail!(
+                    "the condition \"{condition}\" on {entry_path} uses the undeclared variable {variable_name}"
+                );
+            }
+        }
+        let path_parts = entry_path
+            .split('/')
+            .filter(|path_part| !path_part.is_empty())
+            .collect::<Vec<_>>();
+        let Some(dir_entry) = find_entry_mut(structure, &path_parts) else {
+            eyre::bail!(
+                "the condition on {entry_path} applies to a file or folder that doesn't exist"
+            );
+        };
+        match dir_entry {
+            TemplateStructureDirEntryData::Folder {
+                condition: entry_condition,
+                ..
+            }
+            | TemplateStructureDirEntryData::File {
+                condition: entry_condition,
+                ..
+            }
+            | TemplateStructureDirEntryData::EmptyFolder {
+                condition: entry_condition,
+                ..
+            }
+            | TemplateStructureDirEntryData::Symlink {
+                condition: entry_condition,
+                ..
+            } => *entry_condition = Some(condition.clone()),
+        }
+    }
+    Ok(())
+}
+
+fn find_entry_mut<'a>(
+    structure: &'a mut TemplateStructure,
+    path_parts: &[&str],
+) -> Option<&'a mut TemplateStructureDirEntryData> {
+    let (first_path_part, rest_path_parts) = path_parts.split_first()?;
+    let dir_entry = structure.get_mut(*first_path_part)?;
+    if rest_path_parts.is_empty() {
+        return Some(dir_entry);
+    }
+    match dir_entry {
+        TemplateStructureDirEntryData::Folder { children, .. } => {
+            find_entry_mut(children, rest_path_parts)
+        }
+        TemplateStructureDirEntryData::File { .. }
+        | TemplateStructureDirEntryData::EmptyFolder { .. }
+        | TemplateStructureDirEntryData::Symlink { .. } => None,
+    }
+}
+
+/// The permission bits of a file, or `None` where the filesystem doesn't have them
+#[cfg(unix)]
+pub fn file_mode(metadata: &fs::Metadata) -> Option<FileMode> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(FileMode(metadata.permissions().mode() & 0o7777))
+}
+#[cfg(not(unix))]
+pub fn file_mode(_metadata: &fs::Metadata) -> Option<FileMode> {
+    None
+}
+
+/// Serializes a structure index with sorted keys, so regenerating an unchanged template gives the same file.
+pub fn serialize_template_structure(structure: &TemplateStructure) -> serde_json::Result<String> {
+    serde_json::to_string(&serde_json::to_value(structure)?)
+}
@@ -1,5 +1,6 @@
 use color_eyre::eyre;
-use serde::Deserialize;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
 use std::{
     cell::{LazyCell, RefCell},
     cmp::Ordering,
@@ -7,27 +8,44 @@ use std::{
     sync::Arc,
 };
 
-use crate::string_ops::scaffy_string_replacement;
-
-const SOURCE: &'static str = "https://cdn.jsdelivr.net/gh/hydrogenmacro/scaffy@master/templates";
+use crate::{
+    registry::REGISTRIES,
+    string_ops::ScaffyReplacer,
+    template_source::TemplateSource,
+    template_vars::{Condition, TemplateVariable, VariableValues},
+};
 
 thread_local! {
+/// Every known template, keyed by its id: its `path`, or `registry/path` if an earlier registry already has that `path`
 pub static TEMPLATE_INFOS: RefCell<LazyCell<HashMap<ArcStr, TemplateInfo>>> = RefCell::new(LazyCell::new(|| HashMap::new()));
 }
 
 pub type ArcStr = Arc<str>;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TemplateInfo {
+    /// Key of this template in [`TEMPLATE_INFOS`]
+    #[serde(skip)]
+    pub id: ArcStr,
+    /// Name of the registry this template was listed by
+    #[serde(skip)]
+    pub registry: ArcStr,
     pub name: ArcStr,
     pub path: ArcStr,
     pub author: ArcStr,
     pub description: ArcStr,
     pub tags: TemplateInfoTags,
+    /// Values asked for in addition to the project name
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    /// [`Condition`]s keyed by the `/`-separated path of the file or folder they apply to;
+    /// `scaffy index` copies these into the template's structure index
+    #[serde(default)]
+    pub conditions: HashMap<ArcStr, ArcStr>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct TemplateInfoTags {
     pub languages: HashMap<ArcStr, Option<ArcStr>>,
     pub frameworks: HashMap<ArcStr, Option<ArcStr>>,
@@ -35,22 +53,116 @@ pub struct TemplateInfoTags {
     pub misc: HashMap<ArcStr, Option<ArcStr>>,
 }
 pub fn fetch_template_info() -> eyre::Result<()> {
-    let unparsed = smol::block_on(surf::get(format!("{}/templates.json", SOURCE)).recv_string())
-        .map_err(eyre::Error::msg)?;
-    let template_infos = serde_json::from_str::<Vec<TemplateInfo>>(&unparsed)?;
-
-    TEMPLATE_INFOS.with(|template_info_cache| {
-        for template_info in template_infos {
-            template_info_cache
-                .borrow_mut()
-                .insert(template_info.path.clone(), template_info);
+    smol::block_on(load_template_info())
+}
+
+/// Lists the templates of every registry into [`TEMPLATE_INFOS`], replacing what was loaded before.
+/// Registries that can't be loaded are skipped, unless none of them can.
+pub async fn load_template_info() -> eyre::Result<()> {
+    let registries = REGISTRIES.with_borrow(|registries| {
+        registries
+            .iter()
+            .map(|registry| (registry.name.clone(), registry.source.clone()))
+            .collect::<Vec<_>>()
+    });
+    let registry_template_infos =
+        join_all(registries.iter().map(|(_, source)| source.list_templates())).await;
+
+    let mut first_err = None;
+    let mut loaded_registry_count = 0;
+    TEMPLATE_INFOS.with_borrow_mut(|template_info_cache| {
+        template_info_cache.clear();
+        for ((registry_name, _), template_infos) in registries.iter().zip(registry_template_infos) {
+            let template_infos = match template_infos {
+                Ok(template_infos) => template_infos,
+                Err(err) => {
+                    log::error!("Could not load registry {registry_name}: {err}");
+                    first_err.get_or_insert(err);
+                    continue;
+                }
+            };
+            loaded_registry_count += 1;
+            for mut template_info in template_infos {
+                template_info.registry = registry_name.clone();
+                template_info.id =
+                    unique_template_id(template_info_cache, registry_name, &template_info.path);
+                template_info_cache.insert(template_info.id.clone(), template_info);
+            }
         }
     });
+    // Ids can point to other templates than before, so structures cached under them are stale
+    TEMPLATE_STRUCTURE_CACHE
+        .with_borrow_mut(|template_structure_cache| template_structure_cache.clear());
+
+    match first_err {
+        Some(err) if loaded_registry_count == 0 => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Picks the id of a template: its `path`, or `<registry name>/<path>` if that is taken by an earlier registry.
+/// Since a template's own `path` can look like a namespaced one, `~2`, `~3`, ... is appended if that is taken too.
+fn unique_template_id(
+    template_infos: &HashMap<ArcStr, TemplateInfo>,
+    registry_name: &str,
+    template_path: &ArcStr,
+) -> ArcStr {
+    if !template_infos.contains_key(template_path) {
+        return template_path.clone();
+    }
+    let namespaced_id = format!("{registry_name}/{template_path}");
+    if !template_infos.contains_key(&*namespaced_id) {
+        return namespaced_id.into();
+    }
+    (2..)
+        .map(|suffix_num| format!("{namespaced_id}~{suffix_num}"))
+        .find(|template_id| !template_infos.contains_key(&**template_id))
+        .unwrap()
+        .into()
+}
+
+pub fn get_template_info(template_id: &str) -> eyre::Result<TemplateInfo> {
+    TEMPLATE_INFOS
+        .with_borrow(|template_infos| template_infos.get(template_id).cloned())
+        .ok_or_else(|| eyre::eyre!("unknown template \"{template_id}\""))
+}
 
-    Ok(())
+/// Finds the id of the template with `template_path` in the registry named `registry_name`.
+pub fn find_template_id(registry_name: &str, template_path: &str) -> eyre::Result<ArcStr> {
+    TEMPLATE_INFOS
+        .with_borrow(|template_infos| {
+            template_infos
+                .values()
+                .find(|template_info| {
+                    &*template_info.registry == registry_name
+                        && &*template_info.path == template_path
+                })
+                .map(|template_info| template_info.id.clone())
+        })
+        .ok_or_else(|| {
+            eyre::eyre!("the registry \"{registry_name}\" has no template \"{template_path}\"")
+        })
 }
 
-#[derive(Deserialize, Clone)]
+/// Finds the source a template should be fetched from, along with the template's `path` within it.
+pub fn locate_template(template_id: &str) -> eyre::Result<(Arc<dyn TemplateSource>, ArcStr)> {
+    let TemplateInfo {
+        registry: registry_name,
+        path: template_path,
+        ..
+    } = get_template_info(template_id)?;
+    let source = REGISTRIES
+        .with_borrow(|registries| {
+            registries
+                .iter()
+                .find(|registry| registry.name == registry_name)
+                .map(|registry| registry.source.clone())
+        })
+        .ok_or_else(|| eyre::eyre!("unknown registry \"{registry_name}\""))?;
+    Ok((source, template_path))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(
     rename_all = "camelCase",
     rename_all_fields = "camelCase",
@@ -60,16 +172,104 @@ pub enum TemplateStructureDirEntryData {
     Folder {
         inject_project_info: bool,
         children: HashMap<ArcStr, TemplateStructureDirEntryData>,
+        /// The folder and its contents are only generated if this [`Condition`] holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<ArcStr>,
     },
     File {
         inject_project_info: bool,
+        /// The file is only generated if this [`Condition`] holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<ArcStr>,
+        /// Permissions the file is generated with; only recorded for executable files
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<FileMode>,
+    },
+    /// A folder that is generated without anything in it
+    EmptyFolder {
+        inject_project_info: bool,
+        /// The folder is only generated if this [`Condition`] holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<ArcStr>,
     },
+    Symlink {
+        inject_project_info: bool,
+        /// Path the link points to, relative to the folder containing it; it can't point outside of the project
+        target: ArcStr,
+        /// The link is only generated if this [`Condition`] holds
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<ArcStr>,
+    },
+}
+
+/// Unix permission bits of a template file, written as an octal string like `"755"` in structure indexes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMode(pub u32);
+impl FileMode {
+    pub fn is_executable(&self) -> bool {
+        self.0 & 0o111 != 0
+    }
+}
+impl Serialize for FileMode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:o}", self.0))
+    }
+}
+impl<'de> Deserialize<'de> for FileMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mode = ArcStr::deserialize(deserializer)?;
+        match u32::from_str_radix(&mode, 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(FileMode(mode)),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid file mode \"{mode}\", expected octal permission bits like \"755\""
+            ))),
+        }
+    }
+}
+impl TemplateStructureDirEntryData {
+    pub fn condition(&self) -> Option<&ArcStr> {
+        match self {
+            TemplateStructureDirEntryData::Folder { condition, .. }
+            | TemplateStructureDirEntryData::File { condition, .. }
+            | TemplateStructureDirEntryData::EmptyFolder { condition, .. }
+            | TemplateStructureDirEntryData::Symlink { condition, .. } => condition.as_ref(),
+        }
+    }
 }
 
 pub type TemplateStructure = HashMap<ArcStr, TemplateStructureDirEntryData>;
+
+/// Removes every entry whose condition doesn't hold for `variable_values`, along with its children.
+pub fn filter_template_structure(
+    template_structure: &TemplateStructure,
+    variable_values: &VariableValues,
+) -> eyre::Result<TemplateStructure> {
+    let mut filtered_structure = TemplateStructure::new();
+    for (dir_entry_name, dir_entry) in template_structure {
+        if let Some(condition) = dir_entry.condition()
+            && !Condition::parse(condition)?.evaluate(variable_values)
+        {
+            continue;
+        }
+        let filtered_dir_entry = match dir_entry {
+            TemplateStructureDirEntryData::Folder {
+                inject_project_info,
+                children,
+                condition,
+            } => TemplateStructureDirEntryData::Folder {
+                inject_project_info: *inject_project_info,
+                children: filter_template_structure(children, variable_values)?,
+                condition: condition.clone(),
+            },
+            file => file.clone(),
+        };
+        filtered_structure.insert(dir_entry_name.clone(), filtered_dir_entry);
+    }
+    Ok(filtered_structure)
+}
 pub fn format_template_structure(
     template_structure: &TemplateStructure,
-    project_name: impl AsRef<str>,
+    replacer: &ScaffyReplacer,
 ) -> String {
     let template_entry_sorting_fn = |&(dir_entry_name_1, dir_entry_1, _): &(
         &Arc<str>,
@@ -83,10 +283,14 @@ pub fn format_template_structure(
     )| {
         let mut val1 = 0;
         let mut val2 = 0;
-        if let TemplateStructureDirEntryData::Folder { .. } = dir_entry_1 {
+        if let TemplateStructureDirEntryData::Folder { .. }
+        | TemplateStructureDirEntryData::EmptyFolder { .. } = dir_entry_1
+        {
             val1 = 1;
         }
-        if let TemplateStructureDirEntryData::Folder { .. } = dir_entry_2 {
+        if let TemplateStructureDirEntryData::Folder { .. }
+        | TemplateStructureDirEntryData::EmptyFolder { .. } = dir_entry_2
+        {
             val2 = 1;
         }
         match val1.cmp(&val2) {
@@ -102,8 +306,7 @@ pub fn format_template_structure(
     stack.sort_unstable_by(template_entry_sorting_fn);
 
     while let Some((dir_entry_name, dir_entry, nest_level)) = stack.pop() {
-        let formatted_dir_entry_name =
-            scaffy_string_replacement(dir_entry_name, project_name.as_ref());
+        let formatted_dir_entry_name = replacer.replace(dir_entry_name);
         match dir_entry {
             TemplateStructureDirEntryData::Folder { children, .. } => {
                 let mut children_entries = children
@@ -127,12 +330,33 @@ pub fn format_template_structure(
             TemplateStructureDirEntryData::File { .. } => {
                 if nest_level == 0 {
                     output.push_str("🗎 ");
-                    output.push_str(dir_entry_name);
+                    output.push_str(&formatted_dir_entry_name);
                 } else {
-                    let line = format!("{}🗎 {}", " ".repeat(nest_level * 4), &*dir_entry_name);
+                    let line = format!(
+                        "{}🗎 {}",
+                        " ".repeat(nest_level * 4),
+                        &formatted_dir_entry_name
+                    );
                     output.push_str(&line);
                 }
             }
+            TemplateStructureDirEntryData::EmptyFolder { .. } => {
+                let line = format!(
+                    "{}🖿 {} (empty)",
+                    " ".repeat(nest_level * 4),
+                    &formatted_dir_entry_name
+                );
+                output.push_str(&line);
+            }
+            TemplateStructureDirEntryData::Symlink { target, .. } => {
+                let line = format!(
+                    "{}🔗 {} -> {}",
+                    " ".repeat(nest_level * 4),
+                    &formatted_dir_entry_name,
+                    replacer.replace(target)
+                );
+                output.push_str(&line);
+            }
         }
         output.push('\n');
     }
@@ -143,52 +367,144 @@ thread_local! {
 pub static TEMPLATE_STRUCTURE_CACHE: RefCell<LazyCell<HashMap<ArcStr, TemplateStructure>>> = RefCell::new(LazyCell::new(|| HashMap::new()));
 }
 
-pub fn get_template_structure(template_path: ArcStr) -> eyre::Result<TemplateStructure> {
-    TEMPLATE_STRUCTURE_CACHE.with(|template_structure_cache| {
-        let mut template_structure_cache = template_structure_cache.borrow_mut();
+pub fn get_template_structure(template_id: ArcStr) -> eyre::Result<TemplateStructure> {
+    smol::block_on(load_template_structure(template_id))
+}
 
-        if !template_structure_cache.contains_key(&template_path) {
-            let unparsed_data = smol::block_on(
-                surf::get(format!(
-                    "{}/__scaffy_template_contents/{}.json",
-                    SOURCE,
-                    template_path.as_ref()
-                ))
-                .recv_string(),
-            )
-            .map_err(eyre::Error::msg)?;
+/// Fetches the structure of a template, or takes it from [`TEMPLATE_STRUCTURE_CACHE`] if it was already fetched.
+pub async fn load_template_structure(template_id: ArcStr) -> eyre::Result<TemplateStructure> {
+    if let Some(template_structure) = TEMPLATE_STRUCTURE_CACHE
+        .with_borrow(|template_structure_cache| template_structure_cache.get(&template_id).cloned())
+    {
+        return Ok(template_structure);
+    }
+    let (source, template_path) = locate_template(&template_id)?;
+    let template_structure = source.template_structure(&template_path).await?;
+    TEMPLATE_STRUCTURE_CACHE.with_borrow_mut(|template_structure_cache| {
+        template_structure_cache.insert(template_id, template_structure.clone())
+    });
+    Ok(template_structure)
+}
 
-            let template_info = serde_json::from_str::<TemplateStructure>(&unparsed_data)?;
-            template_structure_cache.insert(template_path.clone(), template_info);
+/// Fetches the raw bytes of a template's file, given its `/`-separated path within the template.
+pub async fn get_template_file_contents(
+    template_id: impl AsRef<str>,
+    file_path: impl AsRef<str>,
+) -> eyre::Result<Vec<u8>> {
+    let (source, template_path) = locate_template(template_id.as_ref())?;
+    source
+        .file_contents(&template_path, file_path.as_ref())
+        .await
+}
+
+/// Pins the registry of a template to the revision it serves now, see [`TemplateSource::pinned_source`].
+/// Projects only need it to be updated later on, so failing to is only logged.
+pub async fn pin_template_source(template_id: &str) -> Option<ArcStr> {
+    let (source, _) = locate_template(template_id).ok()?;
+    match source.pinned_source().await {
+        Ok(pinned_source) => pinned_source.map(ArcStr::from),
+        Err(err) => {
+            log::warn!("Could not pin the registry of {template_id} to its revision: {err:#}");
+            None
         }
-        let t = template_structure_cache
-            .get(&template_path)
-            .unwrap()
-            .clone();
-        return Ok(t);
-    })
+    }
 }
 
-pub async fn get_template_file_contents(
-    template_path: impl AsRef<str>,
-    file_parent_path: ArcStr,
-    file_name: ArcStr,
-) -> eyre::Result<String> {
-    let file_url = if &*file_parent_path == "" {
-        format!("{}/{}/{}", SOURCE, template_path.as_ref(), file_name)
-    } else {
-        format!(
-            "{}/{}/{}/{}",
-            SOURCE,
-            template_path.as_ref(),
-            file_parent_path,
-            file_name
-        )
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use super::{
+        TEMPLATE_INFOS, TemplateStructureDirEntryData, get_template_structure, load_template_info,
     };
-    let file_text = surf::get(file_url)
-        .recv_string()
-        .await
-        .map_err(eyre::Error::msg)?;
+    use crate::{
+        registry::{REGISTRIES, Registry},
+        template_source::memory::{MemoryTemplateSource, template_info},
+    };
+
+    fn memory_source(template_paths: &[&str]) -> Arc<MemoryTemplateSource> {
+        let source =
+            template_paths
+                .iter()
+                .fold(MemoryTemplateSource::new(), |source, template_path| {
+                    source.with_template(template_info(template_path), HashMap::new())
+                });
+        Arc::new(source)
+    }
+
+    #[test]
+    fn namespaces_clashing_templates_without_overwriting() {
+        REGISTRIES.with_borrow_mut(|registries| {
+            *registries = vec![
+                Registry::new(
+                    "scaffy",
+                    memory_source(&["rust-service", "company/rust-service"]),
+                )
+                .unwrap(),
+                Registry::new("company", memory_source(&["rust-service", "web-app"])).unwrap(),
+            ];
+        });
+        smol::block_on(load_template_info()).unwrap();
 
-    return Ok(file_text);
+        let registry_and_path = |template_id: &str| {
+            TEMPLATE_INFOS.with_borrow(|template_infos| {
+                let template_info = &template_infos[template_id];
+                (
+                    template_info.registry.to_string(),
+                    template_info.path.to_string(),
+                )
+            })
+        };
+        assert_eq!(
+            TEMPLATE_INFOS.with_borrow(|template_infos| template_infos.len()),
+            4
+        );
+        assert_eq!(
+            registry_and_path("rust-service"),
+            ("scaffy".into(), "rust-service".into())
+        );
+        assert_eq!(
+            registry_and_path("company/rust-service"),
+            ("scaffy".into(), "company/rust-service".into())
+        );
+        assert_eq!(
+            registry_and_path("company/rust-service~2"),
+            ("company".into(), "rust-service".into())
+        );
+        assert_eq!(
+            registry_and_path("web-app"),
+            ("company".into(), "web-app".into())
+        );
+    }
+
+    #[test]
+    fn forgets_cached_structures_on_reload() {
+        let load_with_file = |file_path: &str| {
+            let structure = HashMap::from([(
+                file_path.into(),
+                TemplateStructureDirEntryData::File {
+                    inject_project_info: false,
+                    condition: None,
+                    mode: None,
+                },
+            )]);
+            let source =
+                MemoryTemplateSource::new().with_template(template_info("web-app"), structure);
+            REGISTRIES.with_borrow_mut(|registries| {
+                *registries = vec![Registry::new("scaffy", Arc::new(source)).unwrap()];
+            });
+            smol::block_on(load_template_info()).unwrap();
+        };
+
+        load_with_file("old.txt");
+        assert!(
+            get_template_structure("web-app".into())
+                .unwrap()
+                .contains_key("old.txt")
+        );
+        load_with_file("new.txt");
+        let template_structure = get_template_structure("web-app".into()).unwrap();
+        assert!(template_structure.contains_key("new.txt"));
+        assert!(!template_structure.contains_key("old.txt"));
+    }
 }
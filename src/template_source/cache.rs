@@ -0,0 +1,84 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use smol::fs;
+
+/// Validators sent back to the server to check whether a cached response is still current
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub struct CachedResponse {
+    pub body: Vec<u8>,
+    pub validators: CacheValidators,
+}
+
+/// An on-disk copy of the responses of one remote source, kept under the user's cache directory
+/// (`$XDG_CACHE_HOME/scaffy` on Linux).
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+impl ResponseCache {
+    /// Returns `None` if the platform has no cache directory.
+    /// The cache is kept in a folder named after a hash of the whole `base_url`, so different sources never share one.
+    pub fn for_source(base_url: &str) -> Option<Self> {
+        let source_dir_name = Sha256::digest(base_url.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        dirs::cache_dir().map(|cache_dir| ResponseCache {
+            dir: cache_dir
+                .join("scaffy")
+                .join("sources")
+                .join(source_dir_name),
+        })
+    }
+    fn body_path(&self, relative_path: &str) -> Option<PathBuf> {
+        is_plain_relative_path(relative_path).then(|| self.dir.join("files").join(relative_path))
+    }
+    fn validators_path(&self, relative_path: &str) -> Option<PathBuf> {
+        is_plain_relative_path(relative_path)
+            .then(|| self.dir.join("meta").join(format!("{relative_path}.json")))
+    }
+    pub async fn get(&self, relative_path: &str) -> Option<CachedResponse> {
+        let body = fs::read(self.body_path(relative_path)?).await.ok()?;
+        let validators = match fs::read(self.validators_path(relative_path)?).await {
+            Ok(unparsed) => serde_json::from_slice(&unparsed).unwrap_or_default(),
+            Err(_) => CacheValidators::default(),
+        };
+        Some(CachedResponse { body, validators })
+    }
+    /// Failing to write to the cache only costs a future download, so errors are logged and otherwise ignored.
+    pub async fn put(&self, relative_path: &str, body: &[u8], validators: &CacheValidators) {
+        let (Some(body_path), Some(validators_path)) = (
+            self.body_path(relative_path),
+            self.validators_path(relative_path),
+        ) else {
+            return;
+        };
+        let result = async {
+            for path in [&body_path, &validators_path] {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+            }
+            fs::write(&body_path, body).await?;
+            fs::write(&validators_path, serde_json::to_vec(validators)?).await?;
+            Ok::<(), std::io::Error>(())
+        }
+        .await;
+        if let Err(err) = result {
+            log::warn!("Could not cache {relative_path}: {err}");
+        }
+    }
+}
+
+fn is_plain_relative_path(relative_path: &str) -> bool {
+    Path::new(relative_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
@@ -0,0 +1,246 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::eyre;
+use futures::{FutureExt, future::BoxFuture};
+use surf::StatusCode;
+
+use crate::{
+    template_info::{ArcStr, TemplateInfo, TemplateStructure},
+    template_source::{
+        TemplateSource,
+        cache::{CacheValidators, CachedResponse, ResponseCache},
+        template_structure_file_path,
+    },
+};
+
+const JSDELIVR_GITHUB_PREFIX: &str = "https://cdn.jsdelivr.net/gh/";
+
+/// A jsDelivr URL serving a folder of a GitHub repository at a branch, tag or commit, like
+/// `https://cdn.jsdelivr.net/gh/<owner>/<repo>@<ref>/templates`
+#[derive(Debug, PartialEq)]
+struct JsDelivrGitHubUrl<'a> {
+    owner: &'a str,
+    repo: &'a str,
+    git_ref: &'a str,
+    folder_path: &'a str,
+}
+impl<'a> JsDelivrGitHubUrl<'a> {
+    fn parse(url: &'a str) -> Option<Self> {
+        let (owner, repo_path) = url.strip_prefix(JSDELIVR_GITHUB_PREFIX)?.split_once('/')?;
+        let (repo_at_ref, folder_path) = repo_path.split_once('/').unwrap_or((repo_path, ""));
+        let (repo, git_ref) = repo_at_ref.split_once('@')?;
+        Some(JsDelivrGitHubUrl {
+            owner,
+            repo,
+            git_ref,
+            folder_path,
+        })
+    }
+    fn with_git_ref(&self, git_ref: &str) -> String {
+        let url = format!(
+            "{JSDELIVR_GITHUB_PREFIX}{}/{}@{git_ref}/{}",
+            self.owner, self.repo, self.folder_path
+        );
+        url.trim_end_matches('/').to_string()
+    }
+}
+
+fn is_commit_sha(git_ref: &str) -> bool {
+    git_ref.len() == 40 && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Fetches templates over HTTP from a URL laid out like `/templates`, such as the jsDelivr CDN.
+///
+/// Responses are cached on disk and revalidated with `ETag`/`Last-Modified`; if the server can't be
+/// reached, the cached copy is served instead and the source is marked as stale.
+pub struct HttpTemplateSource {
+    base_url: ArcStr,
+    cache: Option<ResponseCache>,
+    is_stale: AtomicBool,
+}
+impl HttpTemplateSource {
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        HttpTemplateSource {
+            base_url: base_url.into(),
+            cache: ResponseCache::for_source(base_url),
+            is_stale: AtomicBool::new(false),
+        }
+    }
+    async fn get(&self, relative_path: &str) -> eyre::Result<Vec<u8>> {
+        let cached = match &self.cache {
+            Some(cache) => cache.get(relative_path).await,
+            None => None,
+        };
+        let mut request = surf::get(format!("{}/{}", self.base_url, relative_path));
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.validators.etag {
+                request = request.header("If-None-Match", etag.as_str());
+            }
+            if let Some(last_modified) = &cached.validators.last_modified {
+                request = request.header("If-Modified-Since", last_modified.as_str());
+            }
+        }
+
+        let mut response = match request.await {
+            Ok(response) => response,
+            Err(err) => return self.serve_cached(relative_path, cached, eyre::Error::msg(err)),
+        };
+        // A server that is down or rate limiting is no more use than an unreachable one
+        if response.status().is_server_error() || response.status() == StatusCode::TooManyRequests {
+            let err = eyre::eyre!(
+                "{}/{} responded with {}",
+                self.base_url,
+                relative_path,
+                response.status()
+            );
+            return self.serve_cached(relative_path, cached, err);
+        }
+        if response.status() == StatusCode::NotModified
+            && let Some(cached) = cached
+        {
+            return Ok(cached.body);
+        }
+        if !response.status().is_success() {
+            eyre::bail!(
+                "{}/{} responded with {}",
+                self.base_url,
+                relative_path,
+                response.status()
+            );
+        }
+        let body = response.body_bytes().await.map_err(eyre::Error::msg)?;
+        if let Some(cache) = &self.cache {
+            let validators = CacheValidators {
+                etag: response
+                    .header("ETag")
+                    .map(|value| value.last().to_string()),
+                last_modified: response
+                    .header("Last-Modified")
+                    .map(|value| value.last().to_string()),
+            };
+            cache.put(relative_path, &body, &validators).await;
+        }
+        Ok(body)
+    }
+    /// Falls back to the cached copy of a path that couldn't be fetched, marking the source as stale.
+    fn serve_cached(
+        &self,
+        relative_path: &str,
+        cached: Option<CachedResponse>,
+        err: eyre::Error,
+    ) -> eyre::Result<Vec<u8>> {
+        let Some(cached) = cached else {
+            return Err(err);
+        };
+        log::warn!(
+            "Serving cached {relative_path} as {} failed: {err}",
+            self.base_url
+        );
+        self.is_stale.store(true, Ordering::Relaxed);
+        Ok(cached.body)
+    }
+}
+
+impl TemplateSource for HttpTemplateSource {
+    fn list_templates(&self) -> BoxFuture<'_, eyre::Result<Vec<TemplateInfo>>> {
+        async move {
+            let unparsed = self.get("templates.json").await?;
+            Ok(serde_json::from_slice(&unparsed)?)
+        }
+        .boxed()
+    }
+    fn template_structure<'a>(
+        &'a self,
+        template_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<TemplateStructure>> {
+        async move {
+            let unparsed = self
+                .get(&template_structure_file_path(template_path))
+                .await?;
+            Ok(serde_json::from_slice(&unparsed)?)
+        }
+        .boxed()
+    }
+    fn file_contents<'a>(
+        &'a self,
+        template_path: &'a str,
+        file_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<Vec<u8>>> {
+        async move { self.get(&format!("{template_path}/{file_path}")).await }.boxed()
+    }
+    fn is_stale(&self) -> bool {
+        self.is_stale.load(Ordering::Relaxed)
+    }
+    /// Only jsDelivr URLs of GitHub repositories can be pinned, by resolving their branch or tag to a commit.
+    fn pinned_source(&self) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+        async move {
+            let Some(url) = JsDelivrGitHubUrl::parse(&self.base_url) else {
+                return Ok(None);
+            };
+            if is_commit_sha(url.git_ref) {
+                return Ok(Some(self.base_url.to_string()));
+            }
+            let commit_url = format!(
+                "https://api.github.com/repos/{}/{}/commits/{}",
+                url.owner, url.repo, url.git_ref
+            );
+            let mut response = surf::get(&commit_url)
+                .header("Accept", "application/vnd.github.sha")
+                .header("User-Agent", concat!("scaffy/", env!("CARGO_PKG_VERSION")))
+                .await
+                .map_err(eyre::Error::msg)?;
+            if !response.status().is_success() {
+                eyre::bail!("{commit_url} responded with {}", response.status());
+            }
+            let commit_sha = response.body_string().await.map_err(eyre::Error::msg)?;
+            let commit_sha = commit_sha.trim();
+            if !is_commit_sha(commit_sha) {
+                eyre::bail!("{commit_url} responded with the invalid commit \"{commit_sha}\"");
+            }
+            Ok(Some(url.with_git_ref(commit_sha)))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsDelivrGitHubUrl;
+    use crate::template_source::DEFAULT_SOURCE;
+
+    #[test]
+    fn pins_jsdelivr_github_urls_to_a_commit() {
+        let url = JsDelivrGitHubUrl::parse(DEFAULT_SOURCE).unwrap();
+        assert_eq!(
+            url,
+            JsDelivrGitHubUrl {
+                owner: "hydrogenmacro",
+                repo: "scaffy",
+                git_ref: "master",
+                folder_path: "templates",
+            }
+        );
+        let commit_sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            url.with_git_ref(commit_sha),
+            format!("https://cdn.jsdelivr.net/gh/hydrogenmacro/scaffy@{commit_sha}/templates")
+        );
+        assert_eq!(
+            JsDelivrGitHubUrl::parse("https://cdn.jsdelivr.net/gh/someone/templates@v2")
+                .unwrap()
+                .with_git_ref(commit_sha),
+            format!("https://cdn.jsdelivr.net/gh/someone/templates@{commit_sha}")
+        );
+
+        // Without a ref, jsDelivr serves the latest release, which isn't resolved to a commit
+        assert_eq!(
+            JsDelivrGitHubUrl::parse("https://cdn.jsdelivr.net/gh/someone/templates/dir"),
+            None
+        );
+        assert_eq!(
+            JsDelivrGitHubUrl::parse("https://templates.example.com/scaffy"),
+            None
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, WrapErr};
+use futures::{FutureExt, future::BoxFuture};
+
+use crate::{
+    scaffold::is_plain_path_part,
+    template_info::{TemplateInfo, TemplateStructure},
+    template_source::{TemplateSource, template_structure_file_path},
+};
+
+/// Reads templates from a directory on disk laid out like `/templates`.
+pub struct LocalTemplateSource {
+    base_dir: PathBuf,
+}
+impl LocalTemplateSource {
+    pub fn new(base_dir: impl AsRef<Path>) -> Self {
+        LocalTemplateSource {
+            base_dir: base_dir.as_ref().to_owned(),
+        }
+    }
+    async fn read(&self, relative_path: &str) -> eyre::Result<Vec<u8>> {
+        // Template paths come from templates.json, so they're checked to stay inside `base_dir`
+        if !relative_path.split('/').all(is_plain_path_part) {
+            eyre::bail!(
+                "invalid template path \"{relative_path}\"; path parts can't be empty, \".\" or \"..\", or contain backslashes"
+            );
+        }
+        let file_path = self.base_dir.join(relative_path);
+        smol::fs::read(&file_path)
+            .await
+            .wrap_err_with(|| format!("could not read {}", file_path.display()))
+    }
+}
+
+impl TemplateSource for LocalTemplateSource {
+    fn list_templates(&self) -> BoxFuture<'_, eyre::Result<Vec<TemplateInfo>>> {
+        async move {
+            let unparsed = self.read("templates.json").await?;
+            Ok(serde_json::from_slice(&unparsed)?)
+        }
+        .boxed()
+    }
+    fn template_structure<'a>(
+        &'a self,
+        template_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<TemplateStructure>> {
+        async move {
+            let unparsed = self
+                .read(&template_structure_file_path(template_path))
+                .await?;
+            Ok(serde_json::from_slice(&unparsed)?)
+        }
+        .boxed()
+    }
+    fn file_contents<'a>(
+        &'a self,
+        template_path: &'a str,
+        file_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<Vec<u8>>> {
+        async move { self.read(&format!("{template_path}/{file_path}")).await }.boxed()
+    }
+}
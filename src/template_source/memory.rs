@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use color_eyre::eyre;
+use futures::{FutureExt, future::BoxFuture};
+
+use crate::{
+    template_info::{ArcStr, TemplateInfo, TemplateInfoTags, TemplateStructure},
+    template_source::TemplateSource,
+};
+
+/// A template with the given `path` and nothing else of note
+pub fn template_info(path: &str) -> TemplateInfo {
+    TemplateInfo {
+        id: path.into(),
+        registry: "test".into(),
+        name: path.into(),
+        path: path.into(),
+        author: "tester".into(),
+        description: "A test template".into(),
+        tags: TemplateInfoTags {
+            languages: HashMap::new(),
+            frameworks: HashMap::new(),
+            libraries: HashMap::new(),
+            misc: HashMap::new(),
+        },
+        variables: vec![],
+        conditions: HashMap::new(),
+    }
+}
+
+/// Serves templates kept entirely in memory, so tests can run without a network or a filesystem.
+#[derive(Default)]
+pub struct MemoryTemplateSource {
+    templates: Vec<TemplateInfo>,
+    structures: HashMap<ArcStr, TemplateStructure>,
+    files: HashMap<(ArcStr, ArcStr), Vec<u8>>,
+}
+impl MemoryTemplateSource {
+    pub fn new() -> Self {
+        MemoryTemplateSource::default()
+    }
+    pub fn with_template(
+        mut self,
+        template_info: TemplateInfo,
+        structure: TemplateStructure,
+    ) -> Self {
+        self.structures
+            .insert(template_info.path.clone(), structure);
+        self.templates.push(template_info);
+        self
+    }
+    pub fn with_file(
+        mut self,
+        template_path: &str,
+        file_path: &str,
+        contents: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.files
+            .insert((template_path.into(), file_path.into()), contents.into());
+        self
+    }
+}
+
+impl TemplateSource for MemoryTemplateSource {
+    fn list_templates(&self) -> BoxFuture<'_, eyre::Result<Vec<TemplateInfo>>> {
+        async move { Ok(self.templates.clone()) }.boxed()
+    }
+    fn template_structure<'a>(
+        &'a self,
+        template_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<TemplateStructure>> {
+        async move {
+            self.structures
+                .get(template_path)
+                .cloned()
+                .ok_or_else(|| eyre::eyre!("no template at {template_path}"))
+        }
+        .boxed()
+    }
+    fn file_contents<'a>(
+        &'a self,
+        template_path: &'a str,
+        file_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<Vec<u8>>> {
+        async move {
+            self.files
+                .get(&(template_path.into(), file_path.into()))
+                .cloned()
+                .ok_or_else(|| eyre::eyre!("no file at {template_path}/{file_path}"))
+        }
+        .boxed()
+    }
+}
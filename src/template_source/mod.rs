@@ -0,0 +1,131 @@
+pub mod cache;
+pub mod http;
+pub mod local;
+#[cfg(test)]
+pub mod memory;
+
+use std::sync::Arc;
+
+use color_eyre::eyre;
+use futures::{FutureExt, future::BoxFuture};
+
+use crate::template_info::{TemplateInfo, TemplateStructure};
+
+pub use http::HttpTemplateSource;
+pub use local::LocalTemplateSource;
+
+pub const DEFAULT_SOURCE: &str =
+    "https://cdn.jsdelivr.net/gh/hydrogenmacro/scaffy@master/templates";
+
+/// A backend that templates can be listed, inspected and downloaded from.
+///
+/// `template_path` is always the `path` of a [`TemplateInfo`], and `file_path` is a
+/// `/`-separated path relative to the template's root folder.
+pub trait TemplateSource: Send + Sync {
+    fn list_templates(&self) -> BoxFuture<'_, eyre::Result<Vec<TemplateInfo>>>;
+    fn template_structure<'a>(
+        &'a self,
+        template_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<TemplateStructure>>;
+    fn file_contents<'a>(
+        &'a self,
+        template_path: &'a str,
+        file_path: &'a str,
+    ) -> BoxFuture<'a, eyre::Result<Vec<u8>>>;
+    /// Whether anything this source returned came from an offline copy that may be out of date
+    fn is_stale(&self) -> bool {
+        false
+    }
+    /// Another source that keeps serving the templates as this one serves them now, such as the same
+    /// repository at its current commit, so `scaffy update` can fetch the revision a project was
+    /// generated from after it has changed. `None` if this source can't be pinned like that.
+    fn pinned_source(&self) -> BoxFuture<'_, eyre::Result<Option<String>>> {
+        async { Ok(None) }.boxed()
+    }
+}
+
+/// Treats anything starting with `http://` or `https://` as a remote source and
+/// everything else as a path to a local directory.
+pub fn parse_template_source(source: &str) -> Arc<dyn TemplateSource> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        Arc::new(HttpTemplateSource::new(source))
+    } else {
+        Arc::new(LocalTemplateSource::new(source))
+    }
+}
+
+pub fn default_template_source() -> Arc<dyn TemplateSource> {
+    Arc::new(HttpTemplateSource::new(DEFAULT_SOURCE))
+}
+
+fn template_structure_file_path(template_path: &str) -> String {
+    format!("__scaffy_template_contents/{template_path}.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        LocalTemplateSource, TemplateSource,
+        memory::{MemoryTemplateSource, template_info},
+    };
+    use crate::template_info::TemplateStructureDirEntryData;
+
+    #[test]
+    fn memory_source_serves_templates() {
+        let structure = HashMap::from([(
+            "README.md".into(),
+            TemplateStructureDirEntryData::File {
+                inject_project_info: false,
+                condition: None,
+                mode: None,
+            },
+        )]);
+        let source = MemoryTemplateSource::new()
+            .with_template(template_info("test-template"), structure)
+            .with_file("test-template", "README.md", "# Hello");
+
+        let templates = smol::block_on(source.list_templates()).unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(&*templates[0].path, "test-template");
+        let structure = smol::block_on(source.template_structure("test-template")).unwrap();
+        assert!(structure.contains_key("README.md"));
+        let contents = smol::block_on(source.file_contents("test-template", "README.md")).unwrap();
+        assert_eq!(contents, b"# Hello");
+        assert!(smol::block_on(source.file_contents("test-template", "missing")).is_err());
+    }
+
+    #[test]
+    fn local_source_reads_templates_dir() {
+        let source = LocalTemplateSource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates"));
+
+        let templates = smol::block_on(source.list_templates()).unwrap();
+        assert!(
+            templates
+                .iter()
+                .any(|template_info| &*template_info.path == "basic-rust-workspace")
+        );
+        let structure = smol::block_on(source.template_structure("basic-rust-workspace")).unwrap();
+        assert!(structure.contains_key("Cargo.toml"));
+        let contents =
+            smol::block_on(source.file_contents("basic-rust-workspace", ".gitignore")).unwrap();
+        assert!(!contents.is_empty());
+    }
+
+    #[test]
+    fn local_source_stays_inside_its_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_dir = dir.path().join("templates");
+        std::fs::create_dir_all(base_dir.join("web-app")).unwrap();
+        std::fs::write(base_dir.join("web-app/README.md"), "# Web app").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let source = LocalTemplateSource::new(&base_dir);
+
+        assert!(smol::block_on(source.file_contents("web-app", "README.md")).is_ok());
+        assert!(smol::block_on(source.file_contents("..", "secret.txt")).is_err());
+        assert!(smol::block_on(source.file_contents("web-app", "../../secret.txt")).is_err());
+        assert!(smol::block_on(source.file_contents("/", "secret.txt")).is_err());
+        assert!(smol::block_on(source.template_structure("../secret")).is_err());
+    }
+}
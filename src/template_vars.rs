@@ -0,0 +1,395 @@
+use std::{collections::BTreeMap, fmt};
+
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::template_info::ArcStr;
+
+/// A value a template asks for besides the project name, substituted wherever
+/// `@@SCAFFY_VAR_<name>@@` appears in its files and paths.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVariable {
+    pub name: ArcStr,
+    /// Label shown in the wizard; the name is shown if this is missing
+    #[serde(default)]
+    pub prompt: Option<ArcStr>,
+    #[serde(flatten)]
+    pub kind: TemplateVariableKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "type"
+)]
+pub enum TemplateVariableKind {
+    String {
+        #[serde(default)]
+        default: Option<ArcStr>,
+        #[serde(default)]
+        required: bool,
+    },
+    Bool {
+        #[serde(default)]
+        default: bool,
+    },
+    Choice {
+        options: Vec<ArcStr>,
+        /// Defaults to the first option
+        #[serde(default)]
+        default: Option<ArcStr>,
+    },
+    Integer {
+        #[serde(default)]
+        default: Option<i64>,
+        #[serde(default)]
+        min: Option<i64>,
+        #[serde(default)]
+        max: Option<i64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum VariableValue {
+    Bool(bool),
+    Integer(i64),
+    String(ArcStr),
+}
+impl fmt::Display for VariableValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableValue::Bool(value) => write!(f, "{value}"),
+            VariableValue::Integer(value) => write!(f, "{value}"),
+            VariableValue::String(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// The answers given for a template's variables, keyed by variable name
+pub type VariableValues = BTreeMap<ArcStr, VariableValue>;
+
+impl TemplateVariable {
+    pub fn prompt(&self) -> &str {
+        self.prompt.as_deref().unwrap_or(&self.name)
+    }
+    pub fn default_value(&self) -> Option<VariableValue> {
+        match &self.kind {
+            TemplateVariableKind::String { default, .. } => {
+                default.clone().map(VariableValue::String)
+            }
+            TemplateVariableKind::Bool { default } => Some(VariableValue::Bool(*default)),
+            TemplateVariableKind::Choice { options, default } => default
+                .clone()
+                .or_else(|| options.first().cloned())
+                .map(VariableValue::String),
+            TemplateVariableKind::Integer { default, .. } => default.map(VariableValue::Integer),
+        }
+    }
+    /// Whether a value answered before still has the variable's type, and is one of its options or within its
+    /// bounds. Whether a required string is empty isn't checked, since that was up to the answer at the time.
+    pub fn fits_value(&self, value: &VariableValue) -> bool {
+        match (&self.kind, value) {
+            (TemplateVariableKind::String { .. }, VariableValue::String(_))
+            | (TemplateVariableKind::Bool { .. }, VariableValue::Bool(_)) => true,
+            (TemplateVariableKind::Choice { options, .. }, VariableValue::String(value)) => {
+                options.contains(value)
+            }
+            (TemplateVariableKind::Integer { min, max, .. }, VariableValue::Integer(value)) => {
+                min.is_none_or(|min| *value >= min) && max.is_none_or(|max| *value <= max)
+            }
+            _ => false,
+        }
+    }
+    /// Parses and validates a value typed in by the user.
+    pub fn parse_value(&self, input: &str) -> eyre::Result<VariableValue> {
+        match &self.kind {
+            TemplateVariableKind::String { required, .. } => {
+                if *required && input.trim().is_empty() {
+                    eyre::bail!("{} is required", self.prompt());
+                }
+                Ok(VariableValue::String(input.into()))
+            }
+            TemplateVariableKind::Bool { .. } => match input.to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Ok(VariableValue::Bool(true)),
+                "false" | "no" | "n" | "0" => Ok(VariableValue::Bool(false)),
+                _ => eyre::bail!("{} must be true or false", self.prompt()),
+            },
+            TemplateVariableKind::Choice { options, .. } => {
+                if !options.iter().any(|option| &**option == input) {
+                    eyre::bail!("{} must be one of {}", self.prompt(), options.join(", "));
+                }
+                Ok(VariableValue::String(input.into()))
+            }
+            TemplateVariableKind::Integer { min, max, .. } => {
+                let Ok(value) = input.trim().parse::<i64>() else {
+                    eyre::bail!("{} must be a whole number", self.prompt());
+                };
+                if let Some(min) = min
+                    && value < *min
+                {
+                    eyre::bail!("{} must be at least {min}", self.prompt());
+                }
+                if let Some(max) = max
+                    && value > *max
+                {
+                    eyre::bail!("{} must be at most {max}", self.prompt());
+                }
+                Ok(VariableValue::Integer(value))
+            }
+        }
+    }
+}
+
+pub fn variable_placeholder(variable_name: &str) -> String {
+    format!("@@SCAFFY_VAR_{variable_name}@@")
+}
+
+/// Combines `NAME=VALUE` answers with the declared defaults, failing on unknown, invalid or missing answers.
+pub fn resolve_variables<'a>(
+    declared_variables: &[TemplateVariable],
+    answers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> eyre::Result<VariableValues> {
+    resolve_variables_over(declared_variables, VariableValues::new(), answers)
+}
+
+/// Like [`resolve_variables`], but starting from values answered before, e.g. the ones recorded in a
+/// project's provenance. They're kept as they are, rather than parsed again from their text, unless the
+/// variable is no longer declared or the value no longer [fits](TemplateVariable::fits_value) it.
+pub fn resolve_variables_over<'a>(
+    declared_variables: &[TemplateVariable],
+    mut variable_values: VariableValues,
+    answers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> eyre::Result<VariableValues> {
+    variable_values.retain(|name, value| {
+        declared_variables
+            .iter()
+            .any(|variable| variable.name == *name && variable.fits_value(value))
+    });
+    for (name, input) in answers {
+        let Some(variable) = declared_variables
+            .iter()
+            .find(|variable| &*variable.name == name)
+        else {
+            eyre::bail!("the template has no variable named {name}");
+        };
+        variable_values.insert(variable.name.clone(), variable.parse_value(input)?);
+    }
+    for variable in declared_variables {
+        if variable_values.contains_key(&variable.name) {
+            continue;
+        }
+        let value = match variable.default_value() {
+            Some(value) => value,
+            None => variable.parse_value("").map_err(|_| {
+                eyre::eyre!(
+                    "no value given for {}; pass --var {}=<value>",
+                    variable.prompt(),
+                    variable.name
+                )
+            })?,
+        };
+        variable_values.insert(variable.name.clone(), value);
+    }
+    Ok(variable_values)
+}
+
+/// A condition on the variables, written as comparisons joined by `&&` and `||` (`&&` binds tighter).
+/// A comparison is either `NAME`/`!NAME`, true if the value is `true`, a nonzero integer or a nonempty
+/// string, or `NAME == value`/`NAME != value`, comparing against the value as it would be substituted.
+/// Values can't contain `=`, `!`, `&` or `|`, so stray or repeated operators are rejected rather than compared.
+pub struct Condition {
+    any_of: Vec<Vec<Comparison>>,
+}
+
+enum Comparison {
+    Truthy {
+        variable_name: String,
+        negated: bool,
+    },
+    Equals {
+        variable_name: String,
+        value: String,
+        negated: bool,
+    },
+}
+impl Comparison {
+    fn variable_name(&self) -> &str {
+        match self {
+            Comparison::Truthy { variable_name, .. } | Comparison::Equals { variable_name, .. } => {
+                variable_name
+            }
+        }
+    }
+    fn evaluate(&self, variable_values: &VariableValues) -> bool {
+        let variable_value = variable_values.get(self.variable_name());
+        match self {
+            Comparison::Truthy { negated, .. } => {
+                let is_truthy = match variable_value {
+                    Some(VariableValue::Bool(value)) => *value,
+                    Some(VariableValue::Integer(value)) => *value != 0,
+                    Some(VariableValue::String(value)) => !value.is_empty(),
+                    None => false,
+                };
+                is_truthy != *negated
+            }
+            Comparison::Equals { value, negated, .. } => {
+                let is_equal = variable_value
+                    .is_some_and(|variable_value| variable_value.to_string() == *value);
+                is_equal != *negated
+            }
+        }
+    }
+}
+
+impl Condition {
+    pub fn parse(condition: &str) -> eyre::Result<Self> {
+        let mut any_of = vec![];
+        for all_of_str in condition.split("||") {
+            let mut all_of = vec![];
+            for comparison_str in all_of_str.split("&&") {
+                all_of.push(parse_comparison(comparison_str.trim()).ok_or_else(|| {
+                    eyre::eyre!("invalid condition \"{condition}\" at \"{comparison_str}\"")
+                })?);
+            }
+            any_of.push(all_of);
+        }
+        Ok(Condition { any_of })
+    }
+    pub fn evaluate(&self, variable_values: &VariableValues) -> bool {
+        self.any_of.iter().any(|all_of| {
+            all_of
+                .iter()
+                .all(|comparison| comparison.evaluate(variable_values))
+        })
+    }
+    pub fn variable_names(&self) -> impl Iterator<Item = &str> {
+        self.any_of.iter().flatten().map(Comparison::variable_name)
+    }
+}
+
+fn parse_comparison(comparison_str: &str) -> Option<Comparison> {
+    let is_variable_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    for (operator, negated) in [("!=", true), ("==", false)] {
+        if let Some((variable_name, value)) = comparison_str.split_once(operator) {
+            let (variable_name, value) = (variable_name.trim(), value.trim());
+            // A value with operator characters left in it means a stray or repeated operator, like `a == b == c`
+            let has_leftover_operator = value.contains(['=', '!', '&', '|']);
+            return (is_variable_name(variable_name) && !has_leftover_operator).then(|| {
+                Comparison::Equals {
+                    variable_name: variable_name.to_owned(),
+                    value: value.to_owned(),
+                    negated,
+                }
+            });
+        }
+    }
+    let (variable_name, negated) = match comparison_str.strip_prefix('!') {
+        Some(variable_name) => (variable_name.trim(), true),
+        None => (comparison_str, false),
+    };
+    is_variable_name(variable_name).then(|| Comparison::Truthy {
+        variable_name: variable_name.to_owned(),
+        negated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable_values(values: &[(&str, VariableValue)]) -> VariableValues {
+        values
+            .iter()
+            .map(|(name, value)| (ArcStr::from(*name), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let condition = Condition::parse("a || b && c").unwrap();
+        let evaluate = |a, b, c| {
+            condition.evaluate(&variable_values(&[
+                ("a", VariableValue::Bool(a)),
+                ("b", VariableValue::Bool(b)),
+                ("c", VariableValue::Bool(c)),
+            ]))
+        };
+        assert!(evaluate(true, false, false));
+        assert!(evaluate(false, true, true));
+        assert!(!evaluate(false, true, false));
+        assert!(!evaluate(false, false, true));
+    }
+
+    #[test]
+    fn evaluates_truthiness_and_negation() {
+        let values = variable_values(&[
+            ("flag", VariableValue::Bool(false)),
+            ("count", VariableValue::Integer(2)),
+            ("empty", VariableValue::String("".into())),
+        ]);
+        assert!(!Condition::parse("flag").unwrap().evaluate(&values));
+        assert!(Condition::parse("!flag").unwrap().evaluate(&values));
+        assert!(Condition::parse("count").unwrap().evaluate(&values));
+        assert!(Condition::parse("! empty").unwrap().evaluate(&values));
+        assert!(!Condition::parse("missing").unwrap().evaluate(&values));
+        assert!(Condition::parse("!missing").unwrap().evaluate(&values));
+    }
+
+    #[test]
+    fn compares_against_substituted_values() {
+        let values = variable_values(&[
+            ("license", VariableValue::String("MIT".into())),
+            ("port", VariableValue::Integer(8080)),
+            ("tests", VariableValue::Bool(true)),
+        ]);
+        let evaluate = |condition: &str| Condition::parse(condition).unwrap().evaluate(&values);
+        assert!(evaluate("license == MIT"));
+        assert!(evaluate("license==MIT"));
+        assert!(!evaluate("license != MIT"));
+        assert!(evaluate("license != Apache-2.0"));
+        assert!(evaluate("port == 8080"));
+        assert!(evaluate("tests == true"));
+        assert!(!evaluate("missing == "));
+        assert!(evaluate("license == MIT && !missing || port == 1"));
+    }
+
+    #[test]
+    fn lists_the_variables_it_uses() {
+        let condition = Condition::parse("a && b == x || !c").unwrap();
+        assert_eq!(
+            condition.variable_names().collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_conditions() {
+        for condition in [
+            "",
+            "a &&",
+            "|| a",
+            "a & b",
+            "a | b",
+            "a = b",
+            "a == b == c",
+            "a == b != c",
+            "a !== b",
+            "a == !b",
+            "!a == b",
+            "== b",
+            "a b",
+            "!!a",
+            "a-b",
+        ] {
+            assert!(
+                Condition::parse(condition).is_err(),
+                "\"{condition}\" should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,889 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, WrapErr};
+use futures::future::join_all;
+
+use crate::{
+    provenance::{PROVENANCE_FILE_NAME, Provenance, template_hash},
+    scaffold::{
+        PlannedEntry, PlannedEntryKind, create_staging_dir, create_symlink, is_nonempty_dir,
+        merge_staged_files, plan_template_structure, render_file_contents, save_snapshot,
+        set_file_mode,
+    },
+    snapshot::Snapshot,
+    string_ops::ScaffyReplacer,
+    template_index::{file_mode, serialize_template_structure},
+    template_info::{
+        ArcStr, FileMode, TemplateStructure, find_template_id, get_template_info,
+        get_template_structure, locate_template, pin_template_source,
+    },
+    template_source::{TemplateSource, parse_template_source},
+    template_vars::resolve_variables_over,
+};
+
+/// An entry of a project as a template renders it, or as it is in the working tree
+#[derive(Clone, Debug, PartialEq)]
+enum RenderedEntry {
+    File {
+        contents: Vec<u8>,
+        /// Only set for executable files, like in structure indexes
+        mode: Option<FileMode>,
+    },
+    Folder,
+    Symlink {
+        target: PathBuf,
+    },
+}
+impl RenderedEntry {
+    /// Compares everything but file modes where the filesystem doesn't record them, since
+    /// templates' modes aren't applied there either
+    fn is_same_as(&self, other: &RenderedEntry) -> bool {
+        match (self, other) {
+            (
+                RenderedEntry::File { contents, mode },
+                RenderedEntry::File {
+                    contents: other_contents,
+                    mode: other_mode,
+                },
+            ) => contents == other_contents && (mode == other_mode || !cfg!(unix)),
+            _ => self == other,
+        }
+    }
+    fn text(&self) -> Option<&str> {
+        match self {
+            RenderedEntry::File { contents, .. } => str::from_utf8(contents).ok(),
+            _ => None,
+        }
+    }
+}
+
+type RenderedProject = BTreeMap<PathBuf, RenderedEntry>;
+
+/// What `scaffy update` did to each path whose template contents changed
+#[derive(Debug, Default)]
+pub struct UpdateSummary {
+    pub template_id: ArcStr,
+    pub old_template_hash: ArcStr,
+    pub new_template_hash: ArcStr,
+    /// Added to the template and created in the project
+    pub added: Vec<PathBuf>,
+    /// Changed in the template and not locally, so replaced with the template's version
+    pub updated: Vec<PathBuf>,
+    /// Changed in both the template and locally, and merged without conflicts
+    pub merged: Vec<PathBuf>,
+    /// Removed from the template and unchanged locally, so deleted
+    pub removed: Vec<PathBuf>,
+    /// Paths that need to be resolved by hand, with the reason
+    pub conflicts: Vec<(PathBuf, &'static str)>,
+}
+
+/// Brings the project generated into `project_root_dir` up to date with its template by
+/// three-way merging the changes between the revision recorded in its [`Provenance`] and the
+/// current one into the working tree. `answers` override the recorded variable values.
+///
+/// The old revision is rendered from the current template if it hasn't changed, or else taken from
+/// the [`Snapshot`] cache in `snapshots_dir`, or fetched from the pinned source in the provenance.
+///
+/// Every path is merged in memory and staged before anything in the project is changed, and the
+/// changes are undone if moving any of them into place fails, so a failed update leaves the project
+/// and its provenance as they were.
+pub fn update_project(
+    project_root_dir: &Path,
+    answers: &[(&str, &str)],
+    snapshots_dir: &Path,
+) -> eyre::Result<UpdateSummary> {
+    let old_provenance = Provenance::read(project_root_dir)?;
+    let template_id = find_template_id(&old_provenance.registry, &old_provenance.template_path)?;
+    let template_info = get_template_info(&template_id)?;
+    // Pinned before anything is fetched, so the recorded revision can't be older than the fetched files
+    let pinned_source = smol::block_on(pin_template_source(&template_id));
+    let template_structure = get_template_structure(template_id.clone())?;
+    let (source, template_path) = locate_template(&template_id)?;
+
+    // Variables the template no longer declares are dropped, and new ones take their defaults
+    let new_variables = resolve_variables_over(
+        &template_info.variables,
+        old_provenance.variables.clone(),
+        answers.iter().copied(),
+    )?;
+    let new_replacer = ScaffyReplacer::new(&old_provenance.project_name, &new_variables);
+    let new_planned_entries =
+        plan_template_structure(&template_structure, &new_replacer, &new_variables)?;
+    let template_files = fetch_template_files(&*source, &template_path, &new_planned_entries)?;
+    let new_project = render_project(&new_planned_entries, &new_replacer, |template_file_path| {
+        Ok(template_files
+            .get(template_file_path)
+            .cloned()
+            .unwrap_or_default())
+    })?;
+    let serialized_structure = serialize_template_structure(&template_structure)?;
+    let new_template_hash: ArcStr =
+        hash_template_files(&serialized_structure, &template_files).into();
+
+    let old_replacer = ScaffyReplacer::new(&old_provenance.project_name, &old_provenance.variables);
+    // If the template hasn't changed since, its current files for the old variables hash to the recorded
+    // hash, so the old revision can be rendered from them without fetching it
+    let mut unchanged_old_project = None;
+    if let Ok(old_planned_entries) = plan_template_structure(
+        &template_structure,
+        &old_replacer,
+        &old_provenance.variables,
+    ) {
+        let old_template_files =
+            fetch_template_files(&*source, &template_path, &old_planned_entries)?;
+        if hash_template_files(&serialized_structure, &old_template_files)
+            == *old_provenance.template_hash
+        {
+            unchanged_old_project = Some(render_project(
+                &old_planned_entries,
+                &old_replacer,
+                |template_file_path| {
+                    Ok(old_template_files
+                        .get(template_file_path)
+                        .cloned()
+                        .unwrap_or_default())
+                },
+            )?);
+        }
+    }
+    let old_project = match unchanged_old_project {
+        Some(old_project) => old_project,
+        None => render_old_revision(&old_provenance, &old_replacer, snapshots_dir)?,
+    };
+
+    let mut summary = UpdateSummary {
+        template_id,
+        old_template_hash: old_provenance.template_hash.clone(),
+        new_template_hash: new_template_hash.clone(),
+        ..UpdateSummary::default()
+    };
+    let output_paths = old_project
+        .keys()
+        .chain(new_project.keys())
+        .filter(|output_path| **output_path != Path::new(PROVENANCE_FILE_NAME))
+        .collect::<BTreeSet<_>>();
+    let mut merge_actions = vec![];
+    for output_path in output_paths {
+        let merge_action = merge_entry(
+            project_root_dir,
+            output_path,
+            old_project.get(output_path),
+            new_project.get(output_path),
+            &mut summary,
+        )
+        .wrap_err_with(|| format!("could not update {}", output_path.display()))?;
+        if let Some(merge_action) = merge_action {
+            merge_actions.push((output_path, merge_action));
+        }
+    }
+
+    let new_provenance = Provenance {
+        scaffy_version: env!("CARGO_PKG_VERSION").into(),
+        template_hash: new_template_hash,
+        pinned_source,
+        variables: new_variables,
+        ..old_provenance
+    };
+    apply_merge_actions(project_root_dir, &merge_actions, &new_provenance)?;
+    let template_files = template_files.into_iter().collect::<Vec<_>>();
+    save_snapshot(
+        snapshots_dir,
+        &new_provenance,
+        &template_structure,
+        &template_files
+            .iter()
+            .map(|(file_path, file_contents)| (&**file_path, file_contents.clone()))
+            .collect::<Vec<_>>(),
+    );
+    Ok(summary)
+}
+
+/// Renders the revision of the template recorded in `old_provenance`, taking it from the snapshot
+/// cache or else fetching it from the recorded pinned source, which is then cached.
+fn render_old_revision(
+    old_provenance: &Provenance,
+    old_replacer: &ScaffyReplacer,
+    snapshots_dir: &Path,
+) -> eyre::Result<RenderedProject> {
+    if let Some(snapshot) = Snapshot::load(snapshots_dir, &old_provenance.template_hash)? {
+        let old_planned_entries =
+            plan_template_structure(&snapshot.structure, old_replacer, &old_provenance.variables)?;
+        return render_project(&old_planned_entries, old_replacer, |template_file_path| {
+            snapshot.file_contents(template_file_path)
+        });
+    }
+    let Some(pinned_source) = &old_provenance.pinned_source else {
+        eyre::bail!(
+            "the template has changed since this project was generated or last updated, and merging its changes needs the template's old revision, which isn't cached on this machine and can't be fetched since the registry doesn't serve old revisions; update the project on the machine that generated or last updated it, or merge the changes by hand"
+        );
+    };
+    let source = parse_template_source(pinned_source);
+    let old_structure: TemplateStructure =
+        smol::block_on(source.template_structure(&old_provenance.template_path)).wrap_err_with(
+            || format!("could not fetch the template's old revision from {pinned_source}"),
+        )?;
+    let old_planned_entries =
+        plan_template_structure(&old_structure, old_replacer, &old_provenance.variables)?;
+    let old_template_files = fetch_template_files(
+        &*source,
+        &old_provenance.template_path,
+        &old_planned_entries,
+    )
+    .wrap_err_with(|| {
+        format!("could not fetch the template's old revision from {pinned_source}")
+    })?;
+    if hash_template_files(
+        &serialize_template_structure(&old_structure)?,
+        &old_template_files,
+    ) != *old_provenance.template_hash
+    {
+        eyre::bail!(
+            "{pinned_source} doesn't serve the revision of the template this project was generated from"
+        );
+    }
+    save_snapshot(
+        snapshots_dir,
+        old_provenance,
+        &old_structure,
+        &old_template_files
+            .iter()
+            .map(|(file_path, file_contents)| (&**file_path, file_contents.clone()))
+            .collect::<Vec<_>>(),
+    );
+    render_project(&old_planned_entries, old_replacer, |template_file_path| {
+        Ok(old_template_files
+            .get(template_file_path)
+            .cloned()
+            .unwrap_or_default())
+    })
+}
+
+/// Stages what `merge_actions` write along with the new provenance, then moves them into the project,
+/// backing up what they replace or remove so every change is undone if a later one fails.
+fn apply_merge_actions(
+    project_root_dir: &Path,
+    merge_actions: &[(&PathBuf, MergeAction)],
+    new_provenance: &Provenance,
+) -> eyre::Result<()> {
+    let staging_dir = create_staging_dir(&project_root_dir.canonicalize()?)?;
+    let staged_root_dir = staging_dir.path().join("project");
+    fs::create_dir(&staged_root_dir)?;
+    let mut written_paths = vec![];
+    let mut removed_paths = vec![];
+    let mut removed_folder_paths = vec![];
+    for (output_path, merge_action) in merge_actions {
+        match merge_action {
+            MergeAction::Write(entry) => {
+                write_entry(&staged_root_dir.join(output_path), entry)
+                    .wrap_err_with(|| format!("could not update {}", output_path.display()))?;
+                written_paths.push(output_path.as_path());
+            }
+            // Folders are only removed if nothing was put in them, so they're left out of the staging
+            MergeAction::Remove(RenderedEntry::Folder) => {
+                removed_folder_paths.push(output_path.as_path());
+            }
+            MergeAction::Remove(_) => removed_paths.push(output_path.as_path()),
+        }
+    }
+    fs::write(
+        staged_root_dir.join(PROVENANCE_FILE_NAME),
+        new_provenance.to_toml()?,
+    )?;
+    written_paths.push(Path::new(PROVENANCE_FILE_NAME));
+
+    merge_staged_files(
+        &staged_root_dir,
+        project_root_dir,
+        &written_paths,
+        &removed_paths,
+        &staging_dir.path().join("backup"),
+    )?;
+    // Innermost folders first, so folders only left with emptied folders in them are removed too
+    for folder_path in removed_folder_paths.into_iter().rev() {
+        let _ = fs::remove_dir(project_root_dir.join(folder_path));
+    }
+    Ok(())
+}
+
+fn render_project(
+    planned_entries: &[PlannedEntry],
+    replacer: &ScaffyReplacer,
+    template_file_contents: impl Fn(&str) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<RenderedProject> {
+    planned_entries
+        .iter()
+        .map(|planned_entry| {
+            let rendered_entry = render_entry(planned_entry, replacer, &template_file_contents)?;
+            Ok((planned_entry.output_path.clone(), rendered_entry))
+        })
+        .collect()
+}
+
+fn render_entry(
+    planned_entry: &PlannedEntry,
+    replacer: &ScaffyReplacer,
+    template_file_contents: impl Fn(&str) -> eyre::Result<Vec<u8>>,
+) -> eyre::Result<RenderedEntry> {
+    Ok(match &planned_entry.kind {
+        PlannedEntryKind::File {
+            template_file_path,
+            inject_project_info,
+            mode,
+        } => RenderedEntry::File {
+            contents: render_file_contents(
+                template_file_contents(template_file_path)?,
+                *inject_project_info,
+                replacer,
+            ),
+            mode: *mode,
+        },
+        PlannedEntryKind::EmptyFolder => RenderedEntry::Folder,
+        PlannedEntryKind::Symlink { target } => RenderedEntry::Symlink {
+            target: target.clone(),
+        },
+    })
+}
+
+/// Downloads the unrendered contents of every file in `planned_entries` from the template at
+/// `template_path` in `source`, keyed by their path within the template.
+fn fetch_template_files(
+    source: &dyn TemplateSource,
+    template_path: &str,
+    planned_entries: &[PlannedEntry],
+) -> eyre::Result<BTreeMap<ArcStr, Vec<u8>>> {
+    let template_file_paths = planned_entries
+        .iter()
+        .filter_map(|planned_entry| match &planned_entry.kind {
+            PlannedEntryKind::File {
+                template_file_path, ..
+            } => Some(template_file_path.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let file_contents =
+        smol::block_on(join_all(template_file_paths.iter().map(
+            |template_file_path| source.file_contents(template_path, template_file_path),
+        )));
+    template_file_paths
+        .into_iter()
+        .zip(file_contents)
+        .map(|(template_file_path, file_contents)| Ok((template_file_path, file_contents?)))
+        .collect()
+}
+
+fn hash_template_files(
+    serialized_structure: &str,
+    template_files: &BTreeMap<ArcStr, Vec<u8>>,
+) -> String {
+    template_hash(
+        serialized_structure,
+        template_files
+            .iter()
+            .map(|(file_path, file_contents)| (&**file_path, &**file_contents)),
+    )
+}
+
+fn read_working_entry(entry_path: &Path) -> eyre::Result<Option<RenderedEntry>> {
+    let Ok(metadata) = entry_path.symlink_metadata() else {
+        return Ok(None);
+    };
+    Ok(Some(if metadata.is_symlink() {
+        RenderedEntry::Symlink {
+            target: fs::read_link(entry_path)?,
+        }
+    } else if metadata.is_dir() {
+        RenderedEntry::Folder
+    } else {
+        RenderedEntry::File {
+            contents: fs::read(entry_path)?,
+            mode: file_mode(&metadata).filter(FileMode::is_executable),
+        }
+    }))
+}
+
+/// A change to one path of the working tree, worked out before any is made
+#[derive(Debug, PartialEq)]
+enum MergeAction {
+    Write(RenderedEntry),
+    Remove(RenderedEntry),
+}
+
+fn write_entry(entry_path: &Path, entry: &RenderedEntry) -> eyre::Result<()> {
+    if let Some(entry_parent_path) = entry_path.parent() {
+        fs::create_dir_all(entry_parent_path)?;
+    }
+    match entry {
+        RenderedEntry::File { contents, mode } => {
+            fs::write(entry_path, contents)?;
+            if let Some(mode) = mode {
+                smol::block_on(set_file_mode(entry_path, *mode))?;
+            }
+        }
+        RenderedEntry::Folder => fs::create_dir_all(entry_path)?,
+        RenderedEntry::Symlink { target } => smol::block_on(create_symlink(target, entry_path))?,
+    }
+    Ok(())
+}
+
+/// Works out how to apply the template's change to one path, going by its old (`base`) and new
+/// (`theirs`) rendered versions and the version in the working tree (`ours`).
+fn merge_entry(
+    project_root_dir: &Path,
+    output_path: &Path,
+    base: Option<&RenderedEntry>,
+    theirs: Option<&RenderedEntry>,
+    summary: &mut UpdateSummary,
+) -> eyre::Result<Option<MergeAction>> {
+    let is_same = |a: Option<&RenderedEntry>, b: Option<&RenderedEntry>| match (a, b) {
+        (Some(a), Some(b)) => a.is_same_as(b),
+        (None, None) => true,
+        _ => false,
+    };
+    if is_same(base, theirs) {
+        return Ok(None);
+    }
+    let entry_path = project_root_dir.join(output_path);
+    let ours = read_working_entry(&entry_path)?;
+    if is_same(ours.as_ref(), theirs) {
+        return Ok(None);
+    }
+    let output_path = output_path.to_path_buf();
+    // Replacing a folder would take whatever was put in it along
+    if let (Some(RenderedEntry::Folder), Some(theirs)) = (&ours, theirs)
+        && *theirs != RenderedEntry::Folder
+        && is_nonempty_dir(&entry_path)
+    {
+        summary.conflicts.push((
+            output_path,
+            "replaced in the template, but has files in it locally; kept the local version",
+        ));
+        return Ok(None);
+    }
+    Ok(match (base, ours, theirs) {
+        (None, None, Some(theirs)) => {
+            summary.added.push(output_path);
+            Some(MergeAction::Write(theirs.clone()))
+        }
+        (Some(_), None, Some(_)) => {
+            summary
+                .conflicts
+                .push((output_path, "changed in the template, but deleted locally"));
+            None
+        }
+        (Some(base), Some(ours), None) => {
+            if ours.is_same_as(base) {
+                summary.removed.push(output_path);
+                Some(MergeAction::Remove(ours))
+            } else {
+                if !matches!(ours, RenderedEntry::Folder) {
+                    summary.conflicts.push((
+                        output_path,
+                        "removed from the template, but changed locally; kept the local version",
+                    ));
+                }
+                None
+            }
+        }
+        (Some(base), Some(ours), Some(theirs)) if ours.is_same_as(base) => {
+            summary.updated.push(output_path);
+            Some(MergeAction::Write(theirs.clone()))
+        }
+        (base, Some(ours), Some(theirs)) => {
+            let base_text = match base {
+                Some(base) => base.text(),
+                None => Some(""),
+            };
+            let (Some(base_text), Some(ours_text), Some(theirs_text)) =
+                (base_text, ours.text(), theirs.text())
+            else {
+                summary.conflicts.push((
+                    output_path,
+                    "changed in the template and locally, and can't be merged as text; kept the local version",
+                ));
+                return Ok(None);
+            };
+            let mode = match theirs {
+                RenderedEntry::File { mode, .. } => *mode,
+                _ => None,
+            };
+            let contents = match diffy::merge(base_text, ours_text, theirs_text) {
+                Ok(merged_text) => {
+                    summary.merged.push(output_path);
+                    merged_text
+                }
+                Err(conflicted_text) => {
+                    summary.conflicts.push((
+                        output_path,
+                        "changed in the template and locally; conflicts are marked in the file",
+                    ));
+                    conflicted_text
+                }
+            };
+            Some(MergeAction::Write(RenderedEntry::File {
+                contents: contents.into_bytes(),
+                mode,
+            }))
+        }
+        (_, _, _) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use super::{
+        MergeAction, RenderedEntry, UpdateSummary, apply_merge_actions, merge_entry, update_project,
+    };
+    use crate::{
+        provenance::{PROVENANCE_FILE_NAME, Provenance, template_hash},
+        registry::{REGISTRIES, Registry},
+        snapshot::Snapshot,
+        template_index::serialize_template_structure,
+        template_info::{
+            FileMode, TemplateStructure, TemplateStructureDirEntryData, load_template_info,
+        },
+        template_source::memory::{MemoryTemplateSource, template_info},
+        template_vars::{TemplateVariable, TemplateVariableKind, VariableValue, VariableValues},
+    };
+
+    fn file(text: &str) -> RenderedEntry {
+        RenderedEntry::File {
+            contents: text.as_bytes().to_vec(),
+            mode: None,
+        }
+    }
+
+    /// Merges one path whose working tree version is `ours`, returning what would be done and how it's reported
+    fn merge(
+        base: Option<&str>,
+        ours: Option<&str>,
+        theirs: Option<&str>,
+    ) -> (Option<MergeAction>, UpdateSummary) {
+        let project_dir = tempfile::tempdir().unwrap();
+        if let Some(ours) = ours {
+            fs::write(project_dir.path().join("file.txt"), ours).unwrap();
+        }
+        let mut summary = UpdateSummary::default();
+        let merge_action = merge_entry(
+            project_dir.path(),
+            Path::new("file.txt"),
+            base.map(file).as_ref(),
+            theirs.map(file).as_ref(),
+            &mut summary,
+        )
+        .unwrap();
+        (merge_action, summary)
+    }
+
+    #[test]
+    fn leaves_paths_the_template_didnt_change() {
+        let (merge_action, summary) = merge(Some("a\n"), Some("local\n"), Some("a\n"));
+        assert_eq!(merge_action, None);
+        assert!(summary.conflicts.is_empty() && summary.updated.is_empty());
+        assert_eq!(merge(Some("a\n"), Some("b\n"), Some("b\n")).0, None);
+    }
+
+    #[test]
+    fn replaces_and_removes_files_unchanged_locally() {
+        let (merge_action, summary) = merge(Some("a\n"), Some("a\n"), Some("b\n"));
+        assert_eq!(merge_action, Some(MergeAction::Write(file("b\n"))));
+        assert_eq!(summary.updated, [Path::new("file.txt")]);
+
+        let (merge_action, summary) = merge(Some("a\n"), Some("a\n"), None);
+        assert_eq!(merge_action, Some(MergeAction::Remove(file("a\n"))));
+        assert_eq!(summary.removed, [Path::new("file.txt")]);
+
+        let (merge_action, summary) = merge(None, None, Some("new\n"));
+        assert_eq!(merge_action, Some(MergeAction::Write(file("new\n"))));
+        assert_eq!(summary.added, [Path::new("file.txt")]);
+    }
+
+    #[test]
+    fn merges_changes_made_on_both_sides() {
+        let (merge_action, summary) = merge(
+            Some("one\ntwo\nthree\n"),
+            Some("one, edited\ntwo\nthree\n"),
+            Some("one\ntwo\nthree, updated\n"),
+        );
+        assert_eq!(
+            merge_action,
+            Some(MergeAction::Write(file(
+                "one, edited\ntwo\nthree, updated\n"
+            )))
+        );
+        assert_eq!(summary.merged, [Path::new("file.txt")]);
+
+        // A file added both to the template and locally is merged against an empty base
+        let (merge_action, summary) = merge(None, Some("same\n"), Some("same\nmore\n"));
+        assert!(matches!(merge_action, Some(MergeAction::Write(_))));
+        assert_eq!(summary.conflicts.len() + summary.merged.len(), 1);
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let (merge_action, summary) = merge(Some("a\n"), Some("ours\n"), Some("theirs\n"));
+        let Some(MergeAction::Write(merged_file)) = merge_action else {
+            panic!("expected the conflicted file to be written");
+        };
+        let merged_text = merged_file.text().unwrap();
+        assert!(merged_text.contains("<<<<<<<") && merged_text.contains(">>>>>>>"));
+        assert!(merged_text.contains("ours") && merged_text.contains("theirs"));
+        assert_eq!(summary.conflicts.len(), 1);
+
+        let (merge_action, summary) = merge(Some("a\n"), None, Some("b\n"));
+        assert_eq!(merge_action, None);
+        assert_eq!(summary.conflicts.len(), 1);
+
+        let (merge_action, summary) = merge(Some("a\n"), Some("local\n"), None);
+        assert_eq!(merge_action, None);
+        assert_eq!(summary.conflicts.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn applies_mode_changes() {
+        let project_dir = tempfile::tempdir().unwrap();
+        fs::write(project_dir.path().join("run.sh"), "echo hi\n").unwrap();
+        let executable = RenderedEntry::File {
+            contents: b"echo hi\n".to_vec(),
+            mode: Some(FileMode(0o755)),
+        };
+        let mut summary = UpdateSummary::default();
+        let merge_action = merge_entry(
+            project_dir.path(),
+            Path::new("run.sh"),
+            Some(&file("echo hi\n")),
+            Some(&executable),
+            &mut summary,
+        )
+        .unwrap();
+        assert_eq!(merge_action, Some(MergeAction::Write(executable)));
+        assert_eq!(summary.updated, [Path::new("run.sh")]);
+    }
+
+    #[test]
+    fn rolls_back_when_a_change_cant_be_made() {
+        let project_dir = tempfile::tempdir().unwrap();
+        fs::write(project_dir.path().join("a.txt"), "old\n").unwrap();
+        fs::write(project_dir.path().join("b.txt"), "removed\n").unwrap();
+        // A file where the template now has a folder, which `c/d.txt` can't be moved into
+        fs::write(project_dir.path().join("c"), "in the way\n").unwrap();
+        let provenance = greeter_provenance(&greeter_structure(), "");
+        fs::write(
+            project_dir.path().join(PROVENANCE_FILE_NAME),
+            provenance.to_toml().unwrap(),
+        )
+        .unwrap();
+
+        let [a_path, b_path, d_path] = ["a.txt", "b.txt", "c/d.txt"].map(PathBuf::from);
+        let merge_actions = [
+            (&a_path, MergeAction::Write(file("new\n"))),
+            (&b_path, MergeAction::Remove(file("removed\n"))),
+            (&d_path, MergeAction::Write(file("new\n"))),
+        ];
+        let new_provenance = Provenance {
+            template_hash: format!("sha256:{}", "0".repeat(64)).into(),
+            ..provenance.clone()
+        };
+        assert!(apply_merge_actions(project_dir.path(), &merge_actions, &new_provenance).is_err());
+        assert_eq!(
+            fs::read_to_string(project_dir.path().join("a.txt")).unwrap(),
+            "old\n"
+        );
+        assert_eq!(
+            fs::read_to_string(project_dir.path().join("b.txt")).unwrap(),
+            "removed\n"
+        );
+        assert_eq!(Provenance::read(project_dir.path()).unwrap(), provenance);
+        // Nothing is left behind next to the project either
+        let parent_dir = project_dir.path().parent().unwrap();
+        let project_dir_name = project_dir.path().file_name().unwrap().to_string_lossy();
+        assert!(!fs::read_dir(parent_dir).unwrap().any(|dir_entry| {
+            let dir_entry_name = dir_entry.unwrap().file_name();
+            let dir_entry_name = dir_entry_name.to_string_lossy();
+            dir_entry_name.starts_with(".scaffy-staging-")
+                && dir_entry_name.contains(&*project_dir_name)
+        }));
+    }
+
+    const GREETER_NOTES: &str =
+        "@@SCAFFY_VAR_GREETING@@\nsecond\nthird\nfourth @@SCAFFY_VAR_NOTE@@\n";
+
+    fn greeter_structure() -> TemplateStructure {
+        HashMap::from([(
+            "notes.txt".into(),
+            TemplateStructureDirEntryData::File {
+                inject_project_info: true,
+                condition: None,
+                mode: None,
+            },
+        )])
+    }
+
+    /// Loads a registry whose only template, `greeter`, has a `notes.txt` with the given contents
+    fn load_greeter(notes: &str) {
+        let mut greeter = template_info("greeter");
+        greeter.variables = vec![
+            TemplateVariable {
+                name: "GREETING".into(),
+                prompt: None,
+                kind: TemplateVariableKind::String {
+                    default: None,
+                    required: false,
+                },
+            },
+            TemplateVariable {
+                name: "NOTE".into(),
+                prompt: None,
+                kind: TemplateVariableKind::String {
+                    default: None,
+                    required: true,
+                },
+            },
+        ];
+        let source = MemoryTemplateSource::new()
+            .with_template(greeter, greeter_structure())
+            .with_file("greeter", "notes.txt", notes);
+        REGISTRIES.with_borrow_mut(|registries| {
+            *registries = vec![Registry::new("scaffy", Arc::new(source)).unwrap()];
+        });
+        smol::block_on(load_template_info()).unwrap();
+    }
+
+    /// A provenance of `greeter` generated from the given structure and `notes.txt`, with empty variables
+    fn greeter_provenance(structure: &TemplateStructure, notes: &str) -> Provenance {
+        // The recorded NOTE is empty even though it's now required, which only an answer typed in now is checked for
+        let variables = VariableValues::from([
+            ("GREETING".into(), VariableValue::String("".into())),
+            ("NOTE".into(), VariableValue::String("".into())),
+        ]);
+        Provenance {
+            scaffy_version: "0.0.0".into(),
+            registry: "scaffy".into(),
+            template_path: "greeter".into(),
+            template_hash: template_hash(
+                &serialize_template_structure(structure).unwrap(),
+                [("notes.txt", notes.as_bytes())],
+            )
+            .into(),
+            pinned_source: None,
+            project_name: "demo".into(),
+            variables,
+        }
+    }
+
+    #[test]
+    fn updates_projects_without_a_snapshot_while_the_template_is_unchanged() {
+        load_greeter(GREETER_NOTES);
+        let provenance = greeter_provenance(&greeter_structure(), GREETER_NOTES);
+        let snapshots_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        let notes_path = project_dir.path().join("notes.txt");
+        let provenance_path = project_dir.path().join(PROVENANCE_FILE_NAME);
+        fs::write(&notes_path, "\nsecond\nthird\nfourth, edited \n").unwrap();
+        fs::write(&provenance_path, provenance.to_toml().unwrap()).unwrap();
+
+        let summary = update_project(
+            project_dir.path(),
+            &[("GREETING", "hello")],
+            snapshots_dir.path(),
+        )
+        .unwrap();
+        assert_eq!(summary.merged, [Path::new("notes.txt")]);
+        assert_eq!(
+            fs::read_to_string(&notes_path).unwrap(),
+            "hello\nsecond\nthird\nfourth, edited \n"
+        );
+        let updated_provenance = Provenance::read(project_dir.path()).unwrap();
+        assert_eq!(
+            updated_provenance.variables["GREETING"],
+            VariableValue::String("hello".into())
+        );
+        assert_eq!(
+            updated_provenance.variables["NOTE"],
+            VariableValue::String("".into())
+        );
+
+        // Once the template has changed, the old revision can't be rendered again unless it's cached or pinned
+        let changed_provenance = Provenance {
+            template_hash: format!("sha256:{}", "0".repeat(64)).into(),
+            ..updated_provenance.clone()
+        };
+        fs::write(&provenance_path, changed_provenance.to_toml().unwrap()).unwrap();
+        fs::write(&notes_path, "changed\n").unwrap();
+        let err = update_project(
+            project_dir.path(),
+            &[("GREETING", "bye")],
+            snapshots_dir.path(),
+        )
+        .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("isn't cached on this machine"),
+            "{err:#}"
+        );
+        assert_eq!(fs::read_to_string(&notes_path).unwrap(), "changed\n");
+        assert_eq!(
+            Provenance::read(project_dir.path()).unwrap(),
+            changed_provenance
+        );
+    }
+
+    #[test]
+    fn fetches_the_old_revision_from_the_pinned_source() {
+        let old_notes = "hi\nsecond\nthird\nfourth\n";
+        load_greeter("hi\nsecond\nthird\nfourth, updated\n");
+        // The registry as it was when the project was generated, laid out like `/templates`
+        let pinned_source_dir = tempfile::tempdir().unwrap();
+        let write_pinned_notes = |notes: &str| {
+            let contents_dir = pinned_source_dir.path().join("__scaffy_template_contents");
+            fs::create_dir_all(&contents_dir).unwrap();
+            fs::create_dir_all(pinned_source_dir.path().join("greeter")).unwrap();
+            fs::write(
+                contents_dir.join("greeter.json"),
+                serialize_template_structure(&greeter_structure()).unwrap(),
+            )
+            .unwrap();
+            fs::write(pinned_source_dir.path().join("greeter/notes.txt"), notes).unwrap();
+        };
+        write_pinned_notes(old_notes);
+        let provenance = Provenance {
+            pinned_source: Some(pinned_source_dir.path().to_string_lossy().into()),
+            ..greeter_provenance(&greeter_structure(), old_notes)
+        };
+        let snapshots_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        let notes_path = project_dir.path().join("notes.txt");
+        let provenance_path = project_dir.path().join(PROVENANCE_FILE_NAME);
+        fs::write(&notes_path, "hi, edited\nsecond\nthird\nfourth\n").unwrap();
+        fs::write(&provenance_path, provenance.to_toml().unwrap()).unwrap();
+
+        // A source that no longer serves the recorded revision is caught by its hash
+        write_pinned_notes("hi\nsecond\nthird\nfourth, moved on\n");
+        let err = update_project(project_dir.path(), &[], snapshots_dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("doesn't serve"), "{err:#}");
+
+        write_pinned_notes(old_notes);
+        let summary = update_project(project_dir.path(), &[], snapshots_dir.path()).unwrap();
+        assert_eq!(summary.merged, [Path::new("notes.txt")]);
+        assert_eq!(
+            fs::read_to_string(&notes_path).unwrap(),
+            "hi, edited\nsecond\nthird\nfourth, updated\n"
+        );
+        // The fetched revision is cached, so it isn't fetched again
+        assert!(
+            Snapshot::load(snapshots_dir.path(), &provenance.template_hash)
+                .unwrap()
+                .is_some()
+        );
+    }
+}